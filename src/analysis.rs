@@ -0,0 +1,272 @@
+//! Going back over a finished game one move at a time, with what the
+//! computer makes of each position.
+use crate::board::{Board, BoardState};
+use crate::brain;
+use crate::eval::Eval;
+use crate::model::PlayerMode;
+use crate::player::Player;
+use crate::rules::{Move, Rules};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+#[derive(Debug)]
+pub struct Analysis {
+    /// The position before the first move, then after each move.
+    positions: Vec<Board>,
+    moves: Vec<Move>,
+    /// The computer's best move in each position, once it has been worked
+    /// out.
+    evals: Vec<Option<Eval>>,
+    /// Positions for the background search to look at, by step.
+    wanted: Sender<usize>,
+    /// Its answers, by step.
+    found: Receiver<(usize, Eval)>,
+    /// How many moves into the game the position shown is.
+    pub step: usize,
+}
+
+impl Analysis {
+    /// Replays the game on `board` from its starting position. Games whose
+    /// moves aren't all known, like those mirrored from the server, can't
+    /// be gone over.
+    pub fn new(board: &Board) -> Option<Analysis> {
+        let positions = replay(board)?;
+        // boards can't be copied, so the search gets a replay of its own
+        let (wanted, found) = think(replay(board)?);
+        let mut analysis = Analysis {
+            positions,
+            moves: board.moves.clone(),
+            evals: vec![None; board.moves.len() + 1],
+            wanted,
+            found,
+            step: 0,
+        };
+        analysis.go_to(0);
+        Some(analysis)
+    }
+
+    /// The position shown.
+    pub fn board(&self) -> &Board {
+        &self.positions[self.step]
+    }
+
+    /// Shows the position `steps` moves on, or back for negative `steps`,
+    /// stopping at the start and the end of the game.
+    pub fn step_by(&mut self, steps: isize) {
+        let last = self.moves.len() as isize;
+        self.go_to((self.step as isize + steps).clamp(0, last) as usize);
+    }
+
+    fn go_to(&mut self, step: usize) {
+        self.step = step;
+        for position in step.saturating_sub(1)..=step {
+            if self.evals[position].is_none() {
+                let _ = self.wanted.send(position);
+            }
+        }
+    }
+
+    /// Picks up what the background search has found since the last call.
+    pub fn update(&mut self) {
+        for (step, eval) in self.found.try_iter() {
+            self.evals[step] = Some(eval);
+        }
+    }
+
+    /// The move that led to the position shown, whether it was a good one,
+    /// and how the game stands.
+    pub fn describe(&self) -> Vec<String> {
+        let position = self.board();
+        let rules = &position.rules;
+        let mut lines = vec![];
+        if self.step == 0 {
+            let side = position.current_player.get_sigil().to_string();
+            lines.push(format!("The start, {} to move", side));
+        } else {
+            let played = self.moves[self.step - 1];
+            let mover = (-position.current_player).get_sigil().to_string();
+            lines.push(format!(
+                "Move {} of {}: {} plays {}",
+                self.step,
+                self.moves.len(),
+                mover,
+                name(rules, played)
+            ));
+            lines.push(match (self.evals[self.step - 1], self.evals[self.step]) {
+                (Some(best), Some(after))
+                    if best.play != played
+                        && brain::forced_winner(best.score)
+                            != brain::forced_winner(after.score) =>
+                {
+                    format!("A mistake, {} was better", name(rules, best.play))
+                }
+                (Some(_), Some(_)) => "A good move".to_string(),
+                _ => THINKING.to_string(),
+            });
+        }
+        lines.push(match &position.state {
+            BoardState::InGame => match self.evals[self.step] {
+                None => THINKING.to_string(),
+                Some(eval) => match brain::forced_winner(eval.score) {
+                    Some(winner) => format!("{} wins with best play", sigil(winner)),
+                    None => "No forced win in sight".to_string(),
+                },
+            },
+            BoardState::Tie => "The game is a draw".to_string(),
+            BoardState::Winner(winner, line) if line.is_empty() => {
+                format!("{} wins on time", sigil(*winner))
+            }
+            BoardState::Winner(winner, _) => format!("{} wins", sigil(*winner)),
+        });
+        lines
+    }
+}
+
+/// Shown in place of what the computer makes of a position it is still
+/// searching.
+const THINKING: &str = "Thinking...";
+
+/// Searches the positions asked for on a thread of its own, so stepping
+/// through a game never waits on the computer. Each is searched once, the
+/// latest asked for first.
+fn think(positions: Vec<Board>) -> (Sender<usize>, Receiver<(usize, Eval)>) {
+    let (wanted, steps) = mpsc::channel::<usize>();
+    let (found, evals) = mpsc::channel();
+    thread::spawn(move || {
+        let mut queue = vec![];
+        let mut searched = vec![false; positions.len()];
+        loop {
+            queue.extend(steps.try_iter());
+            let step = match queue.pop() {
+                Some(step) => step,
+                None => match steps.recv() {
+                    Ok(step) => step,
+                    // the analysis was closed
+                    Err(_) => return,
+                },
+            };
+            if searched[step] {
+                continue;
+            }
+            searched[step] = true;
+            if found
+                .send((step, brain::best_move(&positions[step])))
+                .is_err()
+            {
+                return;
+            }
+        }
+    });
+    (wanted, evals)
+}
+
+/// Whether every move of the game on `board` is known, so it can be gone
+/// over.
+pub fn available(board: &Board) -> bool {
+    board.moves.len() + 1 == board.history.len()
+}
+
+/// The game on `board` one position at a time: the start, then after each
+/// move. `None` unless the game is `available`.
+pub fn replay(board: &Board) -> Option<Vec<Board>> {
+    if !available(board) {
+        return None;
+    }
+    let mut positions: Vec<Board> = (0..=board.moves.len())
+        .map(|step| {
+            let mut position = Board::new(board.rect, PlayerMode::PlayX, board.rules)
+                .with_setup(board.setup)
+                .with_first(board.first_player());
+            for &play in &board.moves[..step] {
+                position.play(play);
+            }
+            position
+        })
+        .collect();
+    // a game lost on time ends without a move to show it
+    if let Some(last) = positions.last_mut() {
+        last.state = board.state.clone();
+    }
+    Some(positions)
+}
+
+fn sigil(player: Player) -> String {
+    player.get_sigil().to_string()
+}
+
+/// Where a move goes, in rows and columns counted from 1.
+fn name(rules: &Rules, play: Move) -> String {
+    let place = |cell: usize| {
+        format!(
+            "row {}, column {}",
+            rules.row(cell) + 1,
+            rules.column(cell) + 1
+        )
+    };
+    match play {
+        Move::Place(to) => place(to),
+        Move::Slide(from, to) => format!("{} to {}", place(from), place(to)),
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::board::test::board;
+
+    /// Waits for the search of the position shown and the one before it.
+    #[allow(dead_code)]
+    fn settle(analysis: &mut Analysis) {
+        let step = analysis.step;
+        while analysis.evals[step.saturating_sub(1)..=step]
+            .iter()
+            .any(Option::is_none)
+        {
+            thread::sleep(std::time::Duration::from_millis(5));
+            analysis.update();
+        }
+    }
+
+    #[test]
+    fn goes_over_a_game_move_by_move() {
+        let mut board = board(Rules::classic());
+        // O's second move lets X complete the top row
+        for &cell in &[0, 4, 1, 8, 2] {
+            board.play(Move::Place(cell));
+        }
+        let mut analysis = Analysis::new(&board).unwrap();
+        assert_eq!(analysis.describe()[0], "The start, X to move");
+        analysis.step_by(4);
+        settle(&mut analysis);
+        assert_eq!(analysis.board().board, {
+            let mut cells = board.board.clone();
+            cells[2] = crate::Field::Empty;
+            cells
+        });
+        let lines = analysis.describe();
+        assert_eq!(lines[0], "Move 4 of 5: O plays row 3, column 3");
+        assert_eq!(lines[1], "A mistake, row 1, column 3 was better");
+        assert_eq!(lines[2], "X wins with best play");
+        analysis.step_by(10);
+        assert_eq!(analysis.step, 5);
+        assert_eq!(analysis.describe().last().unwrap(), "X wins");
+    }
+    #[test]
+    fn stepping_does_not_wait_for_the_computer() {
+        let mut board = board(Rules::classic());
+        board.play(Move::Place(4));
+        let mut analysis = Analysis::new(&board).unwrap();
+        analysis.step_by(1);
+        // nothing is known until the search has been heard from
+        assert_eq!(analysis.describe()[1..], [THINKING, THINKING]);
+        settle(&mut analysis);
+        assert_eq!(analysis.describe()[1], "A good move");
+    }
+    #[test]
+    fn server_games_cannot_be_gone_over() {
+        let mut board = board(Rules::classic());
+        board.history.push(0);
+        assert!(Analysis::new(&board).is_none());
+    }
+}
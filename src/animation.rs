@@ -0,0 +1,155 @@
+//! Time-based effects on the board: new marks drawn stroke by stroke, the
+//! winning line sweeping across, and a fresh board fading in. The game
+//! itself never waits on them; they only change how the board is drawn.
+use crate::board::{Board, BoardState};
+use crate::config;
+use crate::field::Field;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub settings: config::Animation,
+    /// Time since the animations started running.
+    now: Duration,
+    /// When the board on show was set up.
+    started: Duration,
+    /// When each cell's mark was made, for marks made since then.
+    placed: Vec<Option<Duration>>,
+    /// When the game on show ended, if it has.
+    ended: Option<Duration>,
+    /// The cells as last seen, to notice new marks.
+    cells: Vec<Field>,
+    moves: usize,
+}
+
+impl Animation {
+    pub fn new(settings: config::Animation) -> Animation {
+        Animation {
+            settings,
+            now: Duration::default(),
+            started: Duration::default(),
+            placed: vec![],
+            ended: None,
+            cells: vec![],
+            moves: 0,
+        }
+    }
+
+    /// Moves time on by `elapsed`, starting effects for whatever has
+    /// happened on `board` since the last update.
+    pub fn update(&mut self, board: &Board, elapsed: Duration) {
+        self.now += elapsed;
+        let moves = board.history.len();
+        if moves < self.moves || self.cells.len() != board.board.len() {
+            // a new board fades in whole, with its starting marks already on it
+            self.started = self.now;
+            self.placed = vec![None; board.board.len()];
+            self.ended = None;
+        } else if moves > self.moves {
+            for (cell, (&before, &after)) in
+                self.cells.iter().zip(&board.board).enumerate()
+            {
+                if after != before && after != Field::Empty {
+                    self.placed[cell] = Some(self.now);
+                }
+            }
+        }
+        match board.state {
+            BoardState::InGame => self.ended = None,
+            _ if self.ended.is_none() => self.ended = Some(self.now),
+            _ => {}
+        }
+        self.cells = board.board.clone();
+        self.moves = moves;
+    }
+
+    /// How far along something that started at `since` and takes `length`
+    /// milliseconds is, from 0 to 1.
+    fn progress(&self, since: Duration, length: u64) -> f32 {
+        if !self.settings.enabled || length == 0 {
+            return 1.0;
+        }
+        let elapsed = self.now.checked_sub(since).unwrap_or_default();
+        (elapsed.as_millis() as f32 / length as f32).min(1.0)
+    }
+
+    /// How much of the mark in `cell` has been drawn.
+    pub fn mark(&self, cell: usize) -> f32 {
+        match self.placed.get(cell) {
+            Some(&Some(placed)) => self.progress(placed, self.settings.mark),
+            _ => 1.0,
+        }
+    }
+
+    /// How much of the winning line has been drawn.
+    pub fn line(&self) -> f32 {
+        match self.ended {
+            Some(ended) => self.progress(ended, self.settings.line),
+            None => 1.0,
+        }
+    }
+
+    /// How far a new board has faded in.
+    pub fn fade(&self) -> f32 {
+        self.progress(self.started, self.settings.fade)
+    }
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation::new(config::Animation::default())
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::board::test::board;
+    #[allow(unused_imports)]
+    use crate::rules::{Move, Rules};
+
+    #[test]
+    fn new_marks_are_drawn_over_time() {
+        let mut animation = Animation::default();
+        let mut board = board(Rules::classic());
+        animation.update(&board, Duration::from_millis(0));
+        assert_eq!(animation.fade(), 0.0);
+        board.play(Move::Place(4));
+        animation.update(&board, Duration::from_millis(100));
+        assert_eq!(animation.mark(4), 0.0);
+        assert_eq!(animation.mark(0), 1.0);
+        animation.update(&board, Duration::from_millis(125));
+        assert_eq!(animation.mark(4), 0.5);
+        animation.update(&board, Duration::from_secs(1));
+        assert_eq!(animation.mark(4), 1.0);
+        assert_eq!(animation.fade(), 1.0);
+    }
+    #[test]
+    fn the_winning_line_sweeps_once_the_game_ends() {
+        let mut animation = Animation::default();
+        let mut board = board(Rules::classic());
+        animation.update(&board, Duration::from_secs(1));
+        for &cell in &[0, 3, 1, 4, 2] {
+            board.play(Move::Place(cell));
+        }
+        animation.update(&board, Duration::from_millis(10));
+        assert_eq!(animation.line(), 0.0);
+        animation.update(&board, Duration::from_millis(200));
+        assert_eq!(animation.line(), 0.5);
+    }
+    #[test]
+    fn everything_shows_at_once_when_turned_off() {
+        let settings = config::Animation {
+            enabled: false,
+            ..config::Animation::default()
+        };
+        let mut animation = Animation::new(settings);
+        let mut board = board(Rules::classic());
+        animation.update(&board, Duration::from_millis(0));
+        board.play(Move::Place(4));
+        animation.update(&board, Duration::from_millis(0));
+        assert_eq!(animation.mark(4), 1.0);
+        assert_eq!(animation.fade(), 1.0);
+    }
+}
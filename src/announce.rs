@@ -0,0 +1,240 @@
+//! Game events as lines of text, for screen readers and other tools that
+//! can't see the board. Each line is one event:
+//!
+//! ```text
+//! New game on a 3 by 3 board
+//! X to move
+//! X plays row 2, column 2
+//! O to move
+//! ...
+//! X wins
+//! ```
+use crate::analysis;
+use crate::board::{Board, BoardState};
+use crate::field::Field;
+use crate::rules::{Move, Rules};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+
+/// The target that writes to standard output rather than a file.
+pub const STDOUT: &str = "stdout";
+
+pub struct Announcer {
+    out: Box<dyn Write>,
+    /// The cells as last announced, empty before the first game.
+    cells: Vec<Field>,
+    /// Positions seen so far, to notice new ones.
+    positions: usize,
+    /// Moves announced so far.
+    moves: usize,
+    state: BoardState,
+}
+
+impl fmt::Debug for Announcer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Announcer")
+            .field("cells", &self.cells)
+            .field("positions", &self.positions)
+            .field("moves", &self.moves)
+            .field("state", &self.state)
+            .finish()
+    }
+}
+
+impl Announcer {
+    /// Announces to standard output for `STDOUT`, or else adds to the file
+    /// at `target`.
+    pub fn open(target: &str) -> io::Result<Announcer> {
+        if target == STDOUT {
+            return Ok(Announcer::new(Box::new(io::stdout())));
+        }
+        let file = OpenOptions::new().create(true).append(true).open(target)?;
+        Ok(Announcer::new(Box::new(file)))
+    }
+
+    pub fn new(out: Box<dyn Write>) -> Announcer {
+        Announcer {
+            out,
+            cells: vec![],
+            positions: 0,
+            moves: 0,
+            state: BoardState::InGame,
+        }
+    }
+
+    /// Writes out whatever has happened on `board` since the last call.
+    pub fn announce(&mut self, board: &Board) {
+        let lines = self.describe(board);
+        let written = lines
+            .iter()
+            .try_for_each(|line| writeln!(self.out, "{}", line))
+            .and_then(|_| self.out.flush());
+        if let Err(error) = written {
+            eprintln!("could not announce: {}", error);
+        }
+    }
+
+    /// Stops following the game, so the next board is announced as a new
+    /// one.
+    pub fn forget(&mut self) {
+        self.cells.clear();
+        self.positions = 0;
+        self.moves = 0;
+    }
+
+    /// The events that took `board` from what was last seen to where it is
+    /// now, move by move in the order they were made.
+    pub fn describe(&mut self, board: &Board) -> Vec<String> {
+        let positions = board.history.len();
+        let mut lines = vec![];
+        let fresh = positions < self.positions || self.cells.len() != board.board.len();
+        if fresh {
+            lines.push(format!(
+                "New game on a {} by {} board",
+                board.rules.width, board.rules.height
+            ));
+        } else if positions > self.positions {
+            match analysis::replay(board) {
+                Some(replayed) => {
+                    for (index, &play) in board.moves.iter().enumerate().skip(self.moves)
+                    {
+                        let (before, after) = (&replayed[index], &replayed[index + 1]);
+                        lines.extend(moved(before, after, play));
+                    }
+                }
+                // boards mirrored from the server only show where the marks are
+                None => lines.extend(changed(&board.rules, &self.cells, &board.board)),
+            }
+        }
+        if fresh || positions != self.positions || board.state != self.state {
+            lines.push(match &board.state {
+                BoardState::InGame => {
+                    format!("{} to move", board.current_player.get_sigil().to_string())
+                }
+                BoardState::Tie => "The game is a draw".to_string(),
+                BoardState::Winner(winner, line) if line.is_empty() => {
+                    format!("{} wins on time", winner.get_sigil().to_string())
+                }
+                BoardState::Winner(winner, _) => {
+                    format!("{} wins", winner.get_sigil().to_string())
+                }
+            });
+        }
+        self.cells = board.board.clone();
+        self.positions = positions;
+        self.moves = board.moves.len();
+        self.state = board.state.clone();
+        lines
+    }
+}
+
+/// Where `cell` is, in rows and columns counted from 1.
+fn place(rules: &Rules, cell: usize) -> String {
+    format!(
+        "row {}, column {}",
+        rules.row(cell) + 1,
+        rules.column(cell) + 1
+    )
+}
+
+/// The move `play` that took `before` to `after`, then any marks it made
+/// vanish.
+fn moved(before: &Board, after: &Board, play: Move) -> Vec<String> {
+    let rules = &before.rules;
+    let sigil = before.current_player.get_sigil().to_string();
+    let mut lines = vec![match play {
+        Move::Place(to) => format!("{} plays {}", sigil, place(rules, to)),
+        Move::Slide(from, to) => format!(
+            "{} slides from {} to {}",
+            sigil,
+            place(rules, from),
+            place(rules, to)
+        ),
+    }];
+    for (cell, (&was, &now)) in before.board.iter().zip(&after.board).enumerate() {
+        let slid = match play {
+            Move::Slide(from, _) => from == cell,
+            Move::Place(_) => false,
+        };
+        if was != Field::Empty && now == Field::Empty && !slid {
+            lines.push(format!("{} leaves {}", was.to_string(), place(rules, cell)));
+        }
+    }
+    lines
+}
+
+/// The marks that went down or came off between `before` and `after`, by
+/// cell, for boards whose moves aren't known.
+fn changed(rules: &Rules, before: &[Field], after: &[Field]) -> Vec<String> {
+    let mut lines = vec![];
+    for (cell, (&was, &now)) in before.iter().zip(after).enumerate() {
+        if was == now {
+            continue;
+        }
+        if now != Field::Empty {
+            lines.push(format!("{} plays {}", now.to_string(), place(rules, cell)));
+        } else {
+            lines.push(format!("{} leaves {}", was.to_string(), place(rules, cell)));
+        }
+    }
+    lines
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::board::test::board;
+    #[allow(unused_imports)]
+    use crate::rules::Variant;
+
+    #[test]
+    fn describes_a_game() {
+        let mut announcer = Announcer::new(Box::new(io::sink()));
+        let mut board = board(Rules::classic());
+        assert_eq!(
+            announcer.describe(&board),
+            vec!["New game on a 3 by 3 board", "X to move"]
+        );
+        assert!(announcer.describe(&board).is_empty());
+        board.play(Move::Place(5));
+        assert_eq!(
+            announcer.describe(&board),
+            vec!["X plays row 2, column 3", "O to move"]
+        );
+        for &cell in &[0, 4, 1, 8, 2] {
+            board.play(Move::Place(cell));
+        }
+        assert_eq!(
+            announcer.describe(&board).last().map(String::as_str),
+            Some("O wins")
+        );
+        announcer.forget();
+        assert_eq!(announcer.describe(&board)[0], "New game on a 3 by 3 board");
+    }
+    #[test]
+    fn moves_are_told_in_the_order_they_were_made() {
+        let mut announcer = Announcer::new(Box::new(io::sink()));
+        let mut board = board(Variant::Infinite.rules());
+        announcer.describe(&board);
+        // X's first mark is in a later cell than O's answer
+        for &cell in &[8, 0, 4, 1, 6, 5] {
+            board.play(Move::Place(cell));
+        }
+        announcer.describe(&board);
+        // a move and its answer, seen together; X's oldest mark vanishes
+        board.play(Move::Place(3));
+        board.play(Move::Place(7));
+        assert_eq!(
+            announcer.describe(&board),
+            vec![
+                "X plays row 2, column 1",
+                "X leaves row 3, column 3",
+                "O plays row 3, column 2",
+                "O leaves row 1, column 1",
+                "X to move",
+            ]
+        );
+    }
+}
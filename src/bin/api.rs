@@ -0,0 +1,393 @@
+//! An HTTP service for tools that want the engine without linking Rust. Run
+//! it with `cargo run --bin api [address]`. Bodies are JSON both ways.
+//!
+//! - `POST /games` with `{"variant": "classic", "bot": "o", "difficulty":
+//!   "easy"}` creates a game; every field is optional. Answers `201` with the
+//!   game.
+//! - `GET /games/<id>` fetches a game.
+//! - `POST /games/<id>/moves` with `{"player": "x", "move": {"place": 4}}`
+//!   plays a move, then lets the computer answer if it plays the other side.
+//! - `GET /games/<id>/best-move` asks the engine what to play next.
+//! - `POST /best-move` and `POST /ranking` do the same for any position,
+//!   given as `{"variant": "classic", "cells": ["x", "empty", ...],
+//!   "to_move": "o"}`. The ranking lists every legal move, best first.
+//!
+//! Scores above zero favour O and below zero favour X; a forced win scores
+//! close to 10000 either way.
+//!
+//! Failures answer with `{"error": "..."}`: `400` for unreadable requests,
+//! `404` for unknown games or paths, `409` for moves out of turn or in a
+//! finished game, and `422` for moves to taken cells or other illegal moves.
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tic_tac_toe::board::{BoardState, MoveError};
+use tic_tac_toe::brain::{self, Difficulty};
+use tic_tac_toe::field::Field;
+use tic_tac_toe::games::Game;
+use tic_tac_toe::player::Player;
+use tic_tac_toe::rules::{Move, Variant};
+use tic_tac_toe::setup::Setup;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+#[derive(Debug, Deserialize)]
+struct NewGame {
+    #[serde(default)]
+    variant: Variant,
+    #[serde(default)]
+    setup: Setup,
+    /// The side the computer plays.
+    bot: Option<Player>,
+    #[serde(default)]
+    difficulty: Difficulty,
+}
+
+#[derive(Debug, Deserialize)]
+struct PlayMove {
+    player: Player,
+    #[serde(rename = "move")]
+    play: Move,
+}
+
+/// A position sent in for analysis.
+#[derive(Debug, Deserialize)]
+struct Position {
+    #[serde(default)]
+    variant: Variant,
+    cells: Vec<Field>,
+    to_move: Player,
+}
+
+/// A request that couldn't be served, with its status code.
+#[derive(Debug)]
+struct Failure {
+    status: u16,
+    message: String,
+}
+
+impl Failure {
+    fn new(status: u16, message: impl ToString) -> Failure {
+        Failure {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<MoveError> for Failure {
+    fn from(error: MoveError) -> Failure {
+        let status = match error {
+            MoveError::GameOver | MoveError::OutOfTurn => 409,
+            MoveError::Occupied | MoveError::Illegal => 422,
+        };
+        Failure::new(status, error)
+    }
+}
+
+impl From<serde_json::Error> for Failure {
+    fn from(error: serde_json::Error) -> Failure {
+        Failure::new(400, error)
+    }
+}
+
+impl Position {
+    fn check(&self) -> Result<(), Failure> {
+        let size = self.variant.rules().size();
+        if self.cells.len() != size {
+            return Err(Failure::new(
+                422,
+                format!("a {} board has {} cells", self.variant.to_string(), size),
+            ));
+        }
+        let rules = self.variant.rules();
+        if brain::check_winner(&self.cells, &rules) != BoardState::InGame {
+            return Err(MoveError::GameOver.into());
+        }
+        if rules.legal_moves(&self.cells, self.to_move).is_empty() {
+            return Err(no_moves());
+        }
+        Ok(())
+    }
+}
+
+/// Positions where the side to move can't move have no best move to give.
+fn no_moves() -> Failure {
+    Failure::new(409, "there are no legal moves")
+}
+
+/// Each game has its own lock, so the computer thinking in one game doesn't
+/// hold up the others.
+#[derive(Default)]
+struct Service {
+    next_game: AtomicUsize,
+    games: Mutex<HashMap<u64, Arc<Mutex<Game>>>>,
+}
+
+impl Service {
+    fn game(&self, id: &str) -> Result<(u64, Arc<Mutex<Game>>), Failure> {
+        let missing = || Failure::new(404, format!("there is no game {}", id));
+        let id: u64 = id.parse().map_err(|_| missing())?;
+        let games = self.games.lock().unwrap();
+        let game = games.get(&id).cloned().ok_or_else(missing)?;
+        Ok((id, game))
+    }
+
+    fn describe(id: u64, game: &Game) -> Value {
+        let mut value = serde_json::to_value(game.state()).unwrap();
+        value["id"] = json!(id);
+        value
+    }
+
+    /// The status and body answering `method` on `path`.
+    fn route(
+        &self,
+        method: &Method,
+        path: &str,
+        body: &str,
+    ) -> Result<(u16, Value), Failure> {
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            (Method::Post, ["games"]) => {
+                let new: NewGame = serde_json::from_str(body)?;
+                new.setup
+                    .check(&new.variant.rules())
+                    .map_err(|error| Failure::new(400, error))?;
+                let mut game = Game::new(new.variant, new.setup, new.bot)
+                    .with_difficulty(new.difficulty);
+                game.bot_move();
+                let id = self.next_game.fetch_add(1, Ordering::SeqCst) as u64 + 1;
+                let value = Service::describe(id, &game);
+                let game = Arc::new(Mutex::new(game));
+                self.games.lock().unwrap().insert(id, game);
+                Ok((201, value))
+            }
+            (Method::Get, ["games", id]) => {
+                let (id, game) = self.game(id)?;
+                let game = game.lock().unwrap();
+                Ok((200, Service::describe(id, &game)))
+            }
+            (Method::Post, ["games", id, "moves"]) => {
+                let (id, game) = self.game(id)?;
+                let play: PlayMove = serde_json::from_str(body)?;
+                let mut game = game.lock().unwrap();
+                game.play(play.player, play.play)?;
+                game.bot_move();
+                Ok((200, Service::describe(id, &game)))
+            }
+            (Method::Get, ["games", id, "best-move"]) => {
+                let (_, game) = self.game(id)?;
+                let game = game.lock().unwrap();
+                if game.board.state != BoardState::InGame {
+                    return Err(MoveError::GameOver.into());
+                }
+                let board = &game.board;
+                if board
+                    .rules
+                    .legal_moves(&board.board, board.current_player)
+                    .is_empty()
+                {
+                    return Err(no_moves());
+                }
+                Ok((200, json!(brain::best_move(&game.board))))
+            }
+            (Method::Post, ["best-move"]) => {
+                let position: Position = serde_json::from_str(body)?;
+                position.check()?;
+                let rules = position.variant.rules();
+                let eval = brain::minimax(
+                    &BoardState::InGame,
+                    &position.cells,
+                    &rules,
+                    position.to_move,
+                    0,
+                );
+                Ok((200, json!(eval)))
+            }
+            (Method::Post, ["ranking"]) => {
+                let position: Position = serde_json::from_str(body)?;
+                position.check()?;
+                let rules = position.variant.rules();
+                let ranking =
+                    brain::rank_moves(&position.cells, &rules, position.to_move);
+                Ok((200, json!(ranking)))
+            }
+            _ => Err(Failure::new(404, format!("nothing at {} {}", method, path))),
+        }
+    }
+
+    fn respond(&self, mut request: Request) {
+        let mut body = String::new();
+        let answer = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => self.route(request.method(), request.url(), &body),
+            Err(error) => Err(Failure::new(400, error)),
+        };
+        let (status, value) = answer.unwrap_or_else(|failure| {
+            (failure.status, json!({ "error": failure.message }))
+        });
+        let header: Header = "Content-Type: application/json".parse().unwrap();
+        let response = Response::from_string(value.to_string())
+            .with_status_code(status)
+            .with_header(header);
+        if let Err(error) = request.respond(response) {
+            eprintln!("could not answer a request: {}", error);
+        }
+    }
+}
+
+fn run(server: Server) {
+    let service = Arc::new(Service::default());
+    for request in server.incoming_requests() {
+        let service = service.clone();
+        thread::spawn(move || service.respond(request));
+    }
+}
+
+fn main() {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let server = Server::http(&address).unwrap_or_else(|error| {
+        eprintln!("could not listen on {}: {}", address, error);
+        std::process::exit(1);
+    });
+    println!("serving the engine on http://{}", address);
+    run(server);
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use std::io::Write;
+    #[allow(unused_imports)]
+    use std::net::TcpStream;
+
+    #[allow(dead_code)]
+    fn start() -> String {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_string();
+        thread::spawn(move || run(server));
+        address
+    }
+
+    #[allow(dead_code)]
+    fn call(address: &str, method: &str, path: &str, body: Value) -> (u16, Value) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let body = body.to_string();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\
+             Content-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            address,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let (_, body) = response.split_at(response.find("\r\n\r\n").unwrap() + 4);
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn play_against_the_bot() {
+        let address = start();
+        let (status, game) = call(&address, "POST", "/games", json!({ "bot": "o" }));
+        assert_eq!(status, 201);
+        let path = format!("/games/{}/moves", game["id"]);
+        let (status, game) = call(
+            &address,
+            "POST",
+            &path,
+            json!({ "player": "x", "move": { "place": 4 } }),
+        );
+        assert_eq!(status, 200);
+        assert_eq!(game["cells"][4], "x");
+        assert_eq!(game["to_move"], "x");
+        // the bot's side, then a taken cell
+        let (status, _) = call(
+            &address,
+            "POST",
+            &path,
+            json!({ "player": "o", "move": { "place": 0 } }),
+        );
+        assert_eq!(status, 409);
+        let (status, body) = call(
+            &address,
+            "POST",
+            &path,
+            json!({ "player": "x", "move": { "place": 4 } }),
+        );
+        assert_eq!(status, 422);
+        assert_eq!(body["error"], "that cell is taken");
+        let (status, _) = call(&address, "GET", "/games/99", json!(null));
+        assert_eq!(status, 404);
+    }
+    #[test]
+    fn ranks_a_submitted_position() {
+        let address = start();
+        let mut cells = vec!["empty"; 9];
+        cells[0] = "x";
+        cells[1] = "x";
+        cells[4] = "o";
+        let position = json!({ "cells": cells, "to_move": "o" });
+        let (status, best) = call(&address, "POST", "/best-move", position.clone());
+        assert_eq!(status, 200);
+        assert_eq!(best["move"], json!({ "place": 2 }));
+        let (status, ranking) = call(&address, "POST", "/ranking", position.clone());
+        assert_eq!(status, 200);
+        assert_eq!(ranking.as_array().unwrap().len(), 6);
+        assert_eq!(ranking[0]["move"], json!({ "place": 2 }));
+
+        let (status, _) = call(&address, "POST", "/ranking", json!({ "cells": [] }));
+        assert_eq!(status, 400);
+        let mut won = position;
+        won["cells"][2] = json!("x");
+        let (status, _) = call(&address, "POST", "/ranking", won);
+        assert_eq!(status, 409);
+    }
+    #[test]
+    fn refuses_positions_without_moves() {
+        let address = start();
+        let (status, body) = call(
+            &address,
+            "POST",
+            "/games",
+            json!({ "setup": { "preset": 99 } }),
+        );
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "there is no starting position 99");
+        let (status, body) = call(
+            &address,
+            "POST",
+            "/games",
+            json!({ "setup": { "preset": 4 } }),
+        );
+        assert_eq!(status, 400);
+        assert_eq!(body["error"], "Pillars is for a 7 by 6 board");
+        // a finished draw: every cell is taken and no line is complete
+        let cells = ["x", "o", "x", "x", "o", "o", "o", "x", "x"];
+        let drawn = json!({ "cells": cells, "to_move": "o" });
+        let (status, body) = call(&address, "POST", "/best-move", drawn);
+        assert_eq!(status, 409);
+        assert_eq!(body["error"], "the game is over");
+        let mut won = vec!["empty"; 9];
+        won[0] = "x";
+        won[1] = "x";
+        won[2] = "x";
+        won[4] = "o";
+        won[8] = "o";
+        let won = json!({ "cells": won, "to_move": "o" });
+        let (status, _) = call(&address, "POST", "/best-move", won);
+        assert_eq!(status, 409);
+    }
+}
@@ -0,0 +1,213 @@
+//! Draws a game as SVG pictures without opening a window, so it runs on
+//! machines without a GPU. Run it with
+//! `cargo run --bin export -- [options] [moves...]`.
+//!
+//! Moves are cells counted from 0 along the rows from the top left, like
+//! `4`, or slides from one cell to another, like `3-4`. The last position
+//! is written to standard output, unless `--frames` asks for all of them.
+//!
+//! - `--variant <name>` picks the board, named as in the settings file;
+//!   `classic` by default.
+//! - `--theme <name>` picks one of the built in themes, or one of your own
+//!   from the theme directory in the settings file; the settings' own theme
+//!   by default. Colors the settings replace are replaced here too.
+//! - `--size <pixels>` sets the side of each picture; 600 by default.
+//! - `--numbers` numbers each mark with the move that made it.
+//! - `--frames <directory>` writes every position of the game instead, as
+//!   `000.svg` for the start, `001.svg` after the first move, and so on.
+use nannou::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+use tic_tac_toe::board::Board;
+use tic_tac_toe::config::Config;
+use tic_tac_toe::model::PlayerMode;
+use tic_tac_toe::rules::{Move, Variant};
+use tic_tac_toe::svg::{self, Export};
+use tic_tac_toe::theme::{self, Theme};
+
+/// What was asked for on the command line.
+#[derive(Debug)]
+struct Options {
+    variant: Variant,
+    export: Export,
+    frames: Option<PathBuf>,
+    moves: Vec<Move>,
+}
+
+/// The theme called `name`, found the way the app finds it: among the built
+/// in ones and those in the settings' directory, with the settings' colors.
+fn find_theme(config: &Config, name: &str) -> Option<Theme> {
+    theme::load_all(&config.theme.directory)
+        .into_iter()
+        .find(|theme| theme.name == name)
+        .map(|theme| theme.with_colors(&config.colors))
+}
+
+fn parse(args: &[String], config: &Config) -> Result<Options, String> {
+    let mut export = Export::default();
+    // like the app, a theme the settings name but can't find leaves light
+    if let Some(theme) =
+        find_theme(config, config.theme_name()).or_else(|| find_theme(config, "light"))
+    {
+        export.theme = theme;
+    }
+    let mut options = Options {
+        variant: Variant::default(),
+        export,
+        frames: None,
+        moves: vec![],
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--variant" => {
+                let name = value()?;
+                options.variant = serde_json::from_value(name.as_str().into())
+                    .map_err(|_| format!("there is no variant called {:?}", name))?;
+            }
+            "--theme" => {
+                let name = value()?;
+                options.export.theme = find_theme(config, name)
+                    .ok_or_else(|| format!("there is no theme called {:?}", name))?;
+            }
+            "--size" => {
+                let size = value()?;
+                options.export.size = size
+                    .parse()
+                    .ok()
+                    .filter(|&size: &f32| size >= 10.0)
+                    .ok_or_else(|| format!("{:?} is not a size in pixels", size))?;
+            }
+            "--numbers" => options.export.move_numbers = true,
+            "--frames" => options.frames = Some(PathBuf::from(value()?)),
+            play => options.moves.push(parse_move(play)?),
+        }
+    }
+    Ok(options)
+}
+
+/// A cell like `4`, or a slide like `3-4`.
+fn parse_move(text: &str) -> Result<Move, String> {
+    let cell = |text: &str| {
+        text.parse::<usize>()
+            .map_err(|_| format!("{:?} is not a move", text))
+    };
+    match text.find('-') {
+        Some(dash) => Ok(Move::Slide(cell(&text[..dash])?, cell(&text[dash + 1..])?)),
+        None => Ok(Move::Place(cell(text)?)),
+    }
+}
+
+/// The game the moves make on the chosen board.
+fn play(options: &Options) -> Result<Board, String> {
+    let rect = Rect::from_w_h(options.export.size, options.export.size);
+    let mut board = Board::new(rect, PlayerMode::PlayX, options.variant.rules());
+    for (index, &play) in options.moves.iter().enumerate() {
+        let side = board.current_player;
+        if play.target() >= board.board.len() {
+            return Err(format!("move {} is off the board", index + 1));
+        }
+        board
+            .try_play(play, side)
+            .map_err(|error| format!("move {} can't be played: {}", index + 1, error))?;
+    }
+    Ok(board)
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    let config = Config::load(Config::path()).map_err(|error| error.to_string())?;
+    let options = parse(args, &config)?;
+    let board = play(&options)?;
+    let directory = match &options.frames {
+        Some(directory) => directory,
+        None => {
+            print!("{}", svg::position(&board, &options.export));
+            return Ok(());
+        }
+    };
+    let frames = svg::game(&board, &options.export)
+        .ok_or_else(|| "the game's moves are not all known".to_string())?;
+    let failed = |error: std::io::Error| format!("{}: {}", directory.display(), error);
+    fs::create_dir_all(directory).map_err(failed)?;
+    for (index, frame) in frames.iter().enumerate() {
+        let path = directory.join(format!("{:03}.svg", index));
+        fs::write(&path, frame).map_err(failed)?;
+    }
+    eprintln!("wrote {} pictures to {}", frames.len(), directory.display());
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(error) = run(&args) {
+        eprintln!("could not export: {}", error);
+        std::process::exit(1);
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[allow(dead_code)]
+    fn parse_default(args: &[String]) -> Result<Options, String> {
+        parse(args, &Config::default())
+    }
+
+    #[test]
+    fn reads_options_and_moves() {
+        let options =
+            parse_default(&args("--variant gravity --size 300 --numbers 4 3-4"))
+                .unwrap();
+        assert_eq!(options.variant, Variant::Gravity);
+        assert_eq!(options.export.size, 300.0);
+        assert!(options.export.move_numbers);
+        assert_eq!(options.moves, vec![Move::Place(4), Move::Slide(3, 4)]);
+        let error = parse_default(&args("--size")).unwrap_err();
+        assert_eq!(error, "--size needs a value");
+        let error = parse_default(&args("--variant chess")).unwrap_err();
+        assert_eq!(error, "there is no variant called \"chess\"");
+        let error = parse_default(&args("--theme neon")).unwrap_err();
+        assert_eq!(error, "there is no theme called \"neon\"");
+        let error = parse_default(&args("four")).unwrap_err();
+        assert_eq!(error, "\"four\" is not a move");
+    }
+    #[test]
+    fn themes_come_from_the_settings() {
+        let directory =
+            std::env::temp_dir().join(format!("export-themes-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("forest.toml"), "text = \"#f0f0e0\"\n").unwrap();
+        let text = format!(
+            "[theme]\nname = \"forest\"\ndirectory = {:?}\n\
+             [colors]\nbackground = \"#102030\"\n",
+            directory.display().to_string()
+        );
+        let config = Config::parse(&text).unwrap();
+        let picked = parse(&args("4"), &config).unwrap().export.theme;
+        let named = parse(&args("--theme dark"), &config).unwrap().export.theme;
+        fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(picked.name, "forest");
+        assert_eq!(Some(picked.background), config.colors.background);
+        assert_eq!(named.name, "dark");
+        assert_eq!(Some(named.background), config.colors.background);
+    }
+    #[test]
+    fn refuses_moves_that_cannot_be_played() {
+        let options = parse_default(&args("4 4")).unwrap();
+        assert_eq!(
+            play(&options).unwrap_err(),
+            "move 2 can't be played: that cell is taken"
+        );
+        let options = parse_default(&args("9")).unwrap();
+        assert_eq!(play(&options).unwrap_err(), "move 1 is off the board");
+        let options = parse_default(&args("0 3 1 4 2")).unwrap();
+        assert_eq!(play(&options).unwrap().moves.len(), 5);
+    }
+}
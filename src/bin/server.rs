@@ -0,0 +1,653 @@
+//! Hosts any number of games at once for browser front-ends and the lobby,
+//! speaking JSON over WebSocket. Run it with `cargo run --bin server
+//! [address]`. The messages are described in `tic_tac_toe::protocol`.
+use nannou::rand::random_range;
+use std::collections::HashMap;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tic_tac_toe::board::BoardState;
+use tic_tac_toe::games::Game;
+use tic_tac_toe::player::Player;
+use tic_tac_toe::protocol::{Listing, Reply, Request};
+use tic_tac_toe::ratings;
+use tic_tac_toe::rules::Variant;
+use tungstenite::{Message, WebSocket};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:9001";
+
+/// How often a connection stops listening to pass on pushed states.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A game and the clients following it.
+struct Room {
+    game: Game,
+    /// The client in each seat, by `Player::index`.
+    seats: [Option<usize>; 2],
+    /// The names the seated clients gave, if any.
+    names: [Option<String>; 2],
+    watchers: Vec<Sender<String>>,
+    /// The join code of a private game.
+    code: Option<String>,
+}
+
+/// A client waiting for a quick match.
+struct Seeker {
+    client: usize,
+    variant: Variant,
+    name: Option<String>,
+    watcher: Sender<String>,
+}
+
+impl Room {
+    /// The side a newcomer would take, if a person can still sit down.
+    fn open_seat(&self) -> Option<Player> {
+        [Player::Player1, Player::Player2]
+            .iter()
+            .copied()
+            .find(|&side| {
+                self.seats[side.index()].is_none() && self.game.bot != Some(side)
+            })
+    }
+
+    fn push(&mut self, id: u64) {
+        let state = self.state(id);
+        self.tell(&state);
+    }
+
+    fn state(&self, id: u64) -> Reply {
+        Reply::State {
+            game: id,
+            players: self.names.clone(),
+            state: Box::new(self.game.state()),
+        }
+    }
+
+    /// Sends `reply` to everyone following the game.
+    fn tell(&mut self, reply: &Reply) {
+        let text = serde_json::to_string(reply).unwrap();
+        // a send only fails once that client has gone
+        self.watchers
+            .retain(|watcher| watcher.send(text.clone()).is_ok());
+    }
+}
+
+/// Each room has its own lock, so the computer thinking in one game doesn't
+/// hold up the others.
+#[derive(Default)]
+struct Server {
+    next_game: AtomicUsize,
+    next_client: AtomicUsize,
+    rooms: Mutex<HashMap<u64, Arc<Mutex<Room>>>>,
+    seekers: Mutex<Vec<Seeker>>,
+}
+
+impl Server {
+    fn room(&self, id: u64) -> Result<Arc<Mutex<Room>>, Reply> {
+        let rooms = self.rooms.lock().unwrap();
+        rooms
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| Reply::error(format!("there is no game {}", id)))
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_game.fetch_add(1, Ordering::SeqCst) as u64 + 1
+    }
+
+    /// Opens room `id` with its first clients seated, letting the computer
+    /// move if it starts.
+    fn open(&self, id: u64, room: Room) {
+        let room = Arc::new(Mutex::new(room));
+        self.rooms.lock().unwrap().insert(id, room.clone());
+        let mut room = room.lock().unwrap();
+        room.game.bot_move();
+        room.push(id);
+    }
+
+    /// Every room, taken out of the map so that each can be locked without
+    /// holding up everyone else's requests.
+    fn all_rooms(&self) -> Vec<(u64, Arc<Mutex<Room>>)> {
+        let rooms = self.rooms.lock().unwrap();
+        rooms.iter().map(|(&id, room)| (id, room.clone())).collect()
+    }
+
+    /// A six digit code no other game is using.
+    fn new_code(&self) -> String {
+        let codes: Vec<String> = self
+            .all_rooms()
+            .iter()
+            .filter_map(|(_, room)| room.lock().unwrap().code.clone())
+            .collect();
+        loop {
+            let code = format!("{:06}", random_range(0, 1_000_000));
+            if !codes.contains(&code) {
+                return code;
+            }
+        }
+    }
+
+    fn find_code(&self, code: &str) -> Result<(u64, Arc<Mutex<Room>>), Reply> {
+        self.all_rooms()
+            .into_iter()
+            .find(|(_, room)| room.lock().unwrap().code.as_deref() == Some(code))
+            .ok_or_else(|| Reply::error(format!("no game has the code {}", code)))
+    }
+
+    /// The public games still looking for a player, oldest first.
+    fn listings(&self) -> Vec<Listing> {
+        let mut games: Vec<Listing> = self
+            .all_rooms()
+            .iter()
+            .filter_map(|(id, room)| {
+                let room = room.lock().unwrap();
+                if room.code.is_some() || room.game.board.state != BoardState::InGame {
+                    return None;
+                }
+                room.open_seat().map(|side| Listing {
+                    game: *id,
+                    variant: room.game.variant,
+                    side,
+                })
+            })
+            .collect();
+        games.sort_by_key(|listing| listing.game);
+        games
+    }
+
+    /// Drops a client that went away from the quick match queue and frees
+    /// its seats, telling whoever is left. Rooms nobody sits in any more are
+    /// closed.
+    fn forget(&self, client: usize) {
+        let mut seekers = self.seekers.lock().unwrap();
+        seekers.retain(|seeker| seeker.client != client);
+        drop(seekers);
+        for (id, room) in self.all_rooms() {
+            let mut room = room.lock().unwrap();
+            let mut left = false;
+            for side in &[Player::Player1, Player::Player2] {
+                if room.seats[side.index()] == Some(client) {
+                    room.seats[side.index()] = None;
+                    room.names[side.index()] = None;
+                    room.tell(&Reply::Left {
+                        game: id,
+                        side: *side,
+                    });
+                    left = true;
+                }
+            }
+            let empty = left && room.seats.iter().all(|seat| seat.is_none());
+            drop(room);
+            if empty {
+                self.rooms.lock().unwrap().remove(&id);
+            }
+        }
+    }
+
+    fn handle(
+        &self,
+        client: usize,
+        watcher: &Sender<String>,
+        request: Request,
+    ) -> Option<Reply> {
+        match self.try_handle(client, watcher, request) {
+            Ok(reply) => reply,
+            Err(reply) => Some(reply),
+        }
+    }
+
+    fn try_handle(
+        &self,
+        client: usize,
+        watcher: &Sender<String>,
+        request: Request,
+    ) -> Result<Option<Reply>, Reply> {
+        match request {
+            Request::Create {
+                variant,
+                setup,
+                side,
+                bot,
+                difficulty,
+                private,
+                name,
+            } => {
+                setup.check(&variant.rules()).map_err(Reply::error)?;
+                let side = side.unwrap_or_default();
+                let bot = if bot { Some(-side) } else { None };
+                let (mut seats, mut names) = ([None, None], [None, None]);
+                seats[side.index()] = Some(client);
+                names[side.index()] = name;
+                if let Some(bot) = bot {
+                    names[bot.index()] = Some(ratings::bot_name(difficulty));
+                }
+                let code = if private { Some(self.new_code()) } else { None };
+                let id = self.next_id();
+                self.open(
+                    id,
+                    Room {
+                        game: Game::new(variant, setup, bot).with_difficulty(difficulty),
+                        seats,
+                        names,
+                        watchers: vec![watcher.clone()],
+                        code: code.clone(),
+                    },
+                );
+                Ok(Some(Reply::Seated {
+                    game: id,
+                    side,
+                    code,
+                }))
+            }
+            Request::Join { game, code, name } => {
+                let (id, room) = match (game, &code) {
+                    (_, Some(code)) => self.find_code(code)?,
+                    (Some(id), None) => (id, self.room(id)?),
+                    (None, None) => {
+                        return Err(Reply::error("say which game to join"));
+                    }
+                };
+                let mut room = room.lock().unwrap();
+                if room.code.is_some() && code.is_none() {
+                    return Err(Reply::error("that game needs its join code"));
+                }
+                if game.is_some() && game != Some(id) {
+                    return Err(Reply::error("that code is for another game"));
+                }
+                let side = room
+                    .open_seat()
+                    .ok_or_else(|| Reply::error("that game is full"))?;
+                room.seats[side.index()] = Some(client);
+                room.names[side.index()] = name;
+                room.watchers.push(watcher.clone());
+                room.push(id);
+                Ok(Some(Reply::Seated {
+                    game: id,
+                    side,
+                    code: None,
+                }))
+            }
+            Request::QuickMatch { variant, name } => {
+                let mut seekers = self.seekers.lock().unwrap();
+                let partner = seekers.iter().position(|seeker| {
+                    seeker.variant == variant && seeker.client != client
+                });
+                let partner = match partner {
+                    Some(index) => seekers.remove(index),
+                    None => {
+                        if !seekers.iter().any(|seeker| seeker.client == client) {
+                            seekers.push(Seeker {
+                                client,
+                                variant,
+                                name,
+                                watcher: watcher.clone(),
+                            });
+                        }
+                        return Ok(Some(Reply::Waiting));
+                    }
+                };
+                drop(seekers);
+                // whoever waited longest moves first, and hears about the
+                // game before its first state arrives
+                let id = self.next_id();
+                let seated = Reply::Seated {
+                    game: id,
+                    side: Player::Player1,
+                    code: None,
+                };
+                let _ = partner
+                    .watcher
+                    .send(serde_json::to_string(&seated).unwrap());
+                self.open(
+                    id,
+                    Room {
+                        game: Game::new(variant, Default::default(), None),
+                        seats: [Some(partner.client), Some(client)],
+                        names: [partner.name, name],
+                        watchers: vec![partner.watcher, watcher.clone()],
+                        code: None,
+                    },
+                );
+                Ok(Some(Reply::Seated {
+                    game: id,
+                    side: Player::Player2,
+                    code: None,
+                }))
+            }
+            Request::List => Ok(Some(Reply::Games {
+                games: self.listings(),
+            })),
+            Request::Move { game: id, play } => {
+                let room = self.room(id)?;
+                let mut room = room.lock().unwrap();
+                let current = room.game.board.current_player;
+                // a client holding both seats plays whichever side is to move
+                let side = if room.seats[current.index()] == Some(client) {
+                    current
+                } else if room.seats[(-current).index()] == Some(client) {
+                    -current
+                } else {
+                    return Err(Reply::error("you are not playing in that game"));
+                };
+                room.game.play(side, play).map_err(Reply::error)?;
+                room.push(id);
+                if room.game.bot_move() {
+                    room.push(id);
+                }
+                // the mover hears about it through the push like everyone else
+                Ok(None)
+            }
+            Request::State { game: id } => {
+                let room = self.room(id)?;
+                let room = room.lock().unwrap();
+                Ok(Some(room.state(id)))
+            }
+        }
+    }
+}
+
+fn send(socket: &mut WebSocket<TcpStream>, reply: &Reply) -> tungstenite::Result<()> {
+    socket.write_message(Message::Text(serde_json::to_string(reply).unwrap()))
+}
+
+/// Talks to one client until it goes away.
+fn serve(server: Arc<Server>, stream: TcpStream) -> tungstenite::Result<()> {
+    let client = server.next_client.fetch_add(1, Ordering::SeqCst);
+    let result = converse(&server, client, stream);
+    server.forget(client);
+    result
+}
+
+fn converse(
+    server: &Server,
+    client: usize,
+    stream: TcpStream,
+) -> tungstenite::Result<()> {
+    let mut socket = tungstenite::accept(stream).map_err(|error| match error {
+        tungstenite::HandshakeError::Failure(error) => error,
+        tungstenite::HandshakeError::Interrupted(_) => {
+            tungstenite::Error::Io(io::ErrorKind::WouldBlock.into())
+        }
+    })?;
+    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+    let (watcher, pushes) = mpsc::channel();
+    loop {
+        match socket.read_message() {
+            Ok(Message::Text(text)) => {
+                let reply = match serde_json::from_str(&text) {
+                    Ok(request) => server.handle(client, &watcher, request),
+                    Err(error) => Some(Reply::error(error)),
+                };
+                if let Some(reply) = reply {
+                    send(&mut socket, &reply)?;
+                }
+            }
+            Ok(Message::Close(_)) => return Ok(()),
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(ref error))
+                if error.kind() == io::ErrorKind::WouldBlock
+                    || error.kind() == io::ErrorKind::TimedOut => {}
+            Err(error) => return Err(error),
+        }
+        while let Ok(text) = pushes.try_recv() {
+            socket.write_message(Message::Text(text))?;
+        }
+    }
+}
+
+fn run(listener: TcpListener) {
+    let server = Arc::new(Server::default());
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let server = server.clone();
+                thread::spawn(move || {
+                    if let Err(error) = serve(server, stream) {
+                        eprintln!("client left: {}", error);
+                    }
+                });
+            }
+            Err(error) => eprintln!("could not accept a client: {}", error),
+        }
+    }
+}
+
+fn main() {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let listener = TcpListener::bind(&address).unwrap_or_else(|error| {
+        eprintln!("could not listen on {}: {}", address, error);
+        std::process::exit(1);
+    });
+    println!("serving games on ws://{}", address);
+    run(listener);
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use serde_json::{json, Value};
+
+    #[allow(dead_code)]
+    fn start() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("ws://{}", listener.local_addr().unwrap());
+        thread::spawn(move || run(listener));
+        address
+    }
+
+    #[allow(dead_code)]
+    fn exchange<S: io::Read + io::Write>(socket: &mut WebSocket<S>, request: Value) {
+        socket
+            .write_message(Message::Text(request.to_string()))
+            .unwrap();
+    }
+
+    /// The next message of the given type, skipping any others.
+    #[allow(dead_code)]
+    fn expect<S: io::Read + io::Write>(socket: &mut WebSocket<S>, kind: &str) -> Value {
+        loop {
+            let text = socket.read_message().unwrap().into_text().unwrap();
+            let value: Value = serde_json::from_str(&text).unwrap();
+            if value["type"] == kind {
+                return value;
+            }
+        }
+    }
+
+    #[test]
+    fn bot_answers_a_move() {
+        let address = start();
+        let (mut socket, _) = tungstenite::connect(address.as_str()).unwrap();
+        exchange(&mut socket, json!({ "type": "create", "bot": true }));
+        let seated = expect(&mut socket, "seated");
+        let players = expect(&mut socket, "state")["players"].clone();
+        assert_eq!(players, json!([null, "Bot (Hard)"]));
+        assert_eq!(seated["side"], "x");
+        let game = seated["game"].clone();
+        exchange(
+            &mut socket,
+            json!({ "type": "move", "game": game, "move": { "place": 4 } }),
+        );
+        let state = loop {
+            let state = expect(&mut socket, "state");
+            if state["to_move"] == "x" && state["cells"][4] == "x" {
+                break state;
+            }
+        };
+        let marks = state["cells"].as_array().unwrap();
+        assert_eq!(marks.iter().filter(|&v| v == "o").count(), 1);
+    }
+    #[test]
+    fn unknown_starting_positions_are_refused() {
+        let address = start();
+        let (mut socket, _) = tungstenite::connect(address.as_str()).unwrap();
+        exchange(
+            &mut socket,
+            json!({ "type": "create", "setup": { "preset": 99 } }),
+        );
+        assert_eq!(
+            expect(&mut socket, "error")["message"],
+            "there is no starting position 99"
+        );
+        // the connection is still good for a game that can be set up
+        exchange(&mut socket, json!({ "type": "create" }));
+        assert_eq!(expect(&mut socket, "seated")["side"], "x");
+    }
+    #[test]
+    fn two_clients_share_a_game() {
+        let address = start();
+        let (mut host, _) = tungstenite::connect(address.as_str()).unwrap();
+        let (mut guest, _) = tungstenite::connect(address.as_str()).unwrap();
+        exchange(
+            &mut host,
+            json!({ "type": "create", "variant": "gravity", "name": "ann" }),
+        );
+        let game = expect(&mut host, "seated")["game"].clone();
+        exchange(
+            &mut guest,
+            json!({ "type": "join", "game": game, "name": "bob" }),
+        );
+        assert_eq!(expect(&mut guest, "seated")["side"], "o");
+        assert_eq!(
+            expect(&mut guest, "state")["players"],
+            json!(["ann", "bob"])
+        );
+
+        // O can't move first, or join a full game
+        exchange(
+            &mut guest,
+            json!({ "type": "move", "game": game, "move": { "place": 7 } }),
+        );
+        assert_eq!(
+            expect(&mut guest, "error")["message"],
+            "it is not that player's turn"
+        );
+        exchange(&mut guest, json!({ "type": "join", "game": game }));
+        assert_eq!(expect(&mut guest, "error")["message"], "that game is full");
+
+        exchange(
+            &mut host,
+            json!({ "type": "move", "game": game, "move": { "place": 7 } }),
+        );
+        let pushed = loop {
+            let state = expect(&mut guest, "state");
+            if state["to_move"] == "o" {
+                break state;
+            }
+        };
+        assert_eq!(pushed["cells"][7], "x");
+    }
+    #[test]
+    fn private_games_need_their_code() {
+        let address = start();
+        let (mut host, _) = tungstenite::connect(address.as_str()).unwrap();
+        let (mut guest, _) = tungstenite::connect(address.as_str()).unwrap();
+        exchange(&mut host, json!({ "type": "create", "private": true }));
+        let seated = expect(&mut host, "seated");
+        let code = seated["code"].as_str().unwrap().to_string();
+        assert_eq!(code.len(), 6);
+
+        exchange(&mut guest, json!({ "type": "list" }));
+        let listed = expect(&mut guest, "games");
+        assert!(listed["games"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|listing| listing["game"] != seated["game"]));
+        exchange(
+            &mut guest,
+            json!({ "type": "join", "game": seated["game"] }),
+        );
+        assert_eq!(
+            expect(&mut guest, "error")["message"],
+            "that game needs its join code"
+        );
+        exchange(&mut guest, json!({ "type": "join", "code": code }));
+        let joined = expect(&mut guest, "seated");
+        assert_eq!(joined["game"], seated["game"]);
+        assert_eq!(joined["side"], "o");
+    }
+    #[test]
+    fn quick_match_pairs_waiting_players() {
+        let address = start();
+        let (mut first, _) = tungstenite::connect(address.as_str()).unwrap();
+        let (mut second, _) = tungstenite::connect(address.as_str()).unwrap();
+        exchange(
+            &mut first,
+            json!({ "type": "quick-match", "variant": "torus" }),
+        );
+        expect(&mut first, "waiting");
+        exchange(
+            &mut second,
+            json!({ "type": "quick-match", "variant": "torus" }),
+        );
+        let theirs = expect(&mut second, "seated");
+        let ours = expect(&mut first, "seated");
+        assert_eq!(ours["game"], theirs["game"]);
+        assert_eq!(ours["side"], "x");
+        assert_eq!(theirs["side"], "o");
+        assert_eq!(expect(&mut first, "state")["variant"], "torus");
+    }
+    #[test]
+    fn lists_open_public_games() {
+        let address = start();
+        let (mut host, _) = tungstenite::connect(address.as_str()).unwrap();
+        exchange(&mut host, json!({ "type": "create", "variant": "hex" }));
+        let open = expect(&mut host, "seated")["game"].clone();
+        exchange(&mut host, json!({ "type": "create", "bot": true }));
+        let against_bot = expect(&mut host, "seated")["game"].clone();
+        exchange(&mut host, json!({ "type": "list" }));
+        let games = expect(&mut host, "games")["games"].clone();
+        assert_eq!(
+            games,
+            json!([{ "game": open, "variant": "hex", "side": "o" }])
+        );
+        assert_ne!(open, against_bot);
+    }
+    #[test]
+    fn leaving_frees_the_seat_and_closes_empty_games() {
+        let address = start();
+        let (mut host, _) = tungstenite::connect(address.as_str()).unwrap();
+        let (mut guest, _) = tungstenite::connect(address.as_str()).unwrap();
+        exchange(&mut host, json!({ "type": "create" }));
+        let game = expect(&mut host, "seated")["game"].clone();
+        exchange(&mut guest, json!({ "type": "join", "game": game }));
+        expect(&mut guest, "seated");
+        drop(guest);
+        let left = expect(&mut host, "left");
+        assert_eq!(left["game"], game);
+        assert_eq!(left["side"], "o");
+        exchange(&mut host, json!({ "type": "list" }));
+        let games = expect(&mut host, "games")["games"].clone();
+        assert_eq!(
+            games,
+            json!([{ "game": game, "variant": "classic", "side": "o" }])
+        );
+
+        drop(host);
+        let (mut late, _) = tungstenite::connect(address.as_str()).unwrap();
+        // the host's connection closes in the background
+        let message = loop {
+            exchange(&mut late, json!({ "type": "state", "game": game }));
+            let reply = loop {
+                let text = late.read_message().unwrap().into_text().unwrap();
+                let value: Value = serde_json::from_str(&text).unwrap();
+                if value["type"] == "error" || value["type"] == "state" {
+                    break value;
+                }
+            };
+            if reply["type"] == "error" {
+                break reply["message"].clone();
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+        assert_eq!(message, format!("there is no game {}", game));
+    }
+}
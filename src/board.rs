@@ -1,101 +1,333 @@
-use crate::brain;
+use crate::brain::{self, Difficulty};
+use crate::hex;
 use crate::model::PlayerMode;
 use crate::player::Player;
+use crate::rules::{Move, Rules};
+use crate::setup::Setup;
 use crate::Field;
 use nannou::prelude::*;
+use std::collections::VecDeque;
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum BoardState {
-    Winner(Player, (usize, usize, usize)),
+    /// The winner and their line, which is empty if the other side ran out
+    /// of time.
+    Winner(Player, Vec<usize>),
     Tie,
     InGame,
 }
 
+/// Why a move was turned down.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MoveError {
+    GameOver,
+    OutOfTurn,
+    Occupied,
+    Illegal,
+}
+
+impl std::fmt::Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let reason = match self {
+            MoveError::GameOver => "the game is over",
+            MoveError::OutOfTurn => "it is not that player's turn",
+            MoveError::Occupied => "that cell is taken",
+            MoveError::Illegal => "that move is not allowed",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
 #[derive(Debug)]
 pub struct Board {
     pub rect: geom::Rect,
     pub board: Vec<Field>,
+    pub rules: Rules,
+    pub setup: Setup,
     pub player_1: Player,
     pub player_2: Player,
     pub current_player: Player,
     pub state: BoardState,
+    /// The piece picked up by the first click of a slide.
+    pub selected: Option<usize>,
+    /// The cell picked with the arrow keys, once they have been used.
+    pub cursor: Option<usize>,
+    /// Each player's marks, oldest first.
+    pub queues: [VecDeque<usize>; 2],
+    /// Every position so far, as `brain::position_key`s.
+    pub history: Vec<u64>,
+    /// Every move played since the starting position. Boards mirrored from
+    /// the server only have their positions.
+    pub moves: Vec<Move>,
 }
 
 impl Board {
-    pub fn new(rect: geom::Rect, player_mode: PlayerMode) -> Self {
+    pub fn new(rect: geom::Rect, player_mode: PlayerMode, rules: Rules) -> Self {
         let player = Player::from(player_mode);
+        let board: Vec<Field> = (0..rules.size()).map(|_| Field::Empty).collect();
+        let queues = [VecDeque::new(), VecDeque::new()];
         Board {
             rect,
-            board: (0..9).map(|_| Field::Empty).collect(),
+            history: vec![brain::position_key(&board, &queues, Player::Player1)],
+            board,
+            queues,
+            rules,
+            setup: Setup::Empty,
             player_1: player,
             player_2: -player,
             current_player: Player::Player1,
             state: BoardState::InGame,
+            selected: None,
+            cursor: None,
+            moves: vec![],
+        }
+    }
+    /// Fills in the starting position. The side with fewer marks moves first.
+    pub fn with_setup(mut self, setup: Setup) -> Self {
+        self.setup = setup;
+        setup.apply(&self.rules, &mut self.board);
+        self.queues = brain::initial_queues(&self.board);
+        if self.queues[0].len() > self.queues[1].len() {
+            self.current_player = Player::Player2;
+        }
+        self.history = vec![brain::position_key(
+            &self.board,
+            &self.queues,
+            self.current_player,
+        )];
+        self.state = brain::check_winner(&self.board, &self.rules);
+        self
+    }
+    /// Has `first` move first, unless the starting position already says
+    /// who does by leaving one side a mark short.
+    pub fn with_first(mut self, first: Player) -> Self {
+        if self.queues[0].len() == self.queues[1].len() {
+            self.current_player = first;
+            self.history = vec![brain::position_key(&self.board, &self.queues, first)];
         }
+        self
     }
-    pub fn computer_move(&mut self) {
+    /// The side that made the first move, or is to make it.
+    pub fn first_player(&self) -> Player {
+        match self.moves.len() % 2 {
+            0 => self.current_player,
+            _ => -self.current_player,
+        }
+    }
+    pub fn computer_move(&mut self, difficulty: Difficulty) {
         if self.state == BoardState::InGame {
-            let eval = brain::minimax(self.state, &self.board, self.current_player, 0);
-            self.board[eval.position] = self.current_player.get_sigil();
-            self.made_move();
+            let eval = brain::best_move_at(self, difficulty);
+            self.play(eval.play);
+        }
+    }
+
+    pub fn play(&mut self, play: Move) {
+        let queue = &mut self.queues[self.current_player.index()];
+        let sigil = self.current_player.get_sigil();
+        self.rules.make_move(&mut self.board, queue, play, sigil);
+        self.moves.push(play);
+        self.selected = None;
+        self.made_move();
+    }
+
+    /// Whether `player` may make `play` right now.
+    pub fn check_move(&self, play: Move, player: Player) -> Result<(), MoveError> {
+        if self.state != BoardState::InGame {
+            return Err(MoveError::GameOver);
+        }
+        if player != self.current_player {
+            return Err(MoveError::OutOfTurn);
+        }
+        match self.board.get(play.target()) {
+            Some(Field::Empty) => {}
+            Some(_) => return Err(MoveError::Occupied),
+            None => return Err(MoveError::Illegal),
         }
+        if !self.rules.legal_moves(&self.board, player).contains(&play) {
+            return Err(MoveError::Illegal);
+        }
+        Ok(())
+    }
+
+    /// Plays `play` for `player` if `check_move` allows it.
+    pub fn try_play(&mut self, play: Move, player: Player) -> Result<(), MoveError> {
+        self.check_move(play, player)?;
+        self.play(play);
+        Ok(())
     }
 
     pub fn made_move(&mut self) {
-        self.state = brain::check_winner(&self.board);
+        self.state = brain::check_winner(&self.board, &self.rules);
         self.current_player = -self.current_player;
+        self.history.push(brain::position_key(
+            &self.board,
+            &self.queues,
+            self.current_player,
+        ));
+        if self.state == BoardState::InGame
+            && (brain::repetitions(&self.history) >= 3
+                || self
+                    .rules
+                    .legal_moves(&self.board, self.current_player)
+                    .is_empty())
+        {
+            self.state = BoardState::Tie;
+        }
     }
 
-    fn left_column(&self, mouse_x: f32) -> bool {
-        mouse_x < self.rect.left() / 3.0
+    fn column_at(&self, mouse_x: f32) -> usize {
+        let cell_width = self.rect.w() / self.rules.width as f32;
+        let column = ((mouse_x - self.rect.left()) / cell_width).max(0.0) as usize;
+        column.min(self.rules.width - 1)
     }
-    fn right_column(&self, mouse_x: f32) -> bool {
-        mouse_x > self.rect.right() / 3.0
+    fn row_at(&self, mouse_y: f32) -> usize {
+        let cell_height = self.rect.h() / self.rules.height as f32;
+        let row = ((self.rect.top() - mouse_y) / cell_height).max(0.0) as usize;
+        row.min(self.rules.height - 1)
     }
-    fn center_column(&self, mouse_x: f32) -> bool {
-        !self.left_column(mouse_x) && !self.right_column(mouse_x)
+    /// The cell under `point`. Square boards treat clicks past the edge as
+    /// the nearest cell; hex boards have gaps around them, so clicks there
+    /// miss.
+    pub fn cell_at(&self, point: Point2) -> Option<usize> {
+        if self.rules.hex {
+            return hex::cell_at(&self.rect, &self.rules, point);
+        }
+        Some(
+            self.rules
+                .cell(self.column_at(point.x), self.row_at(point.y)),
+        )
     }
-    fn top_row(&self, mouse_y: f32) -> bool {
-        mouse_y > self.rect.top() / 3.0
+    /// The move picked by choosing `cell`, by mouse or keyboard, if that
+    /// completes one.
+    pub fn move_to(&mut self, cell: usize) -> Option<Move> {
+        if self.rules.is_sliding(&self.board, self.current_player) {
+            return self.register_slide(cell);
+        }
+        self.rules.target(&self.board, cell).map(Move::Place)
     }
-    fn bottom_row(&self, mouse_y: f32) -> bool {
-        mouse_y < self.rect.bottom() / 3.0
+    /// The cell a number key picks. On three by three boards 1 to 9 run
+    /// across from the top left like a phone, or from the bottom left like a
+    /// numeric keypad with `numpad`; on boards with gravity they pick the
+    /// column.
+    pub fn digit_cell(&self, digit: usize, numpad: bool) -> Option<usize> {
+        let (width, height) = (self.rules.width, self.rules.height);
+        if digit == 0 || digit > 9 {
+            return None;
+        }
+        if self.rules.gravity {
+            return Some(digit - 1).filter(|&column| column < width);
+        }
+        if (width, height) != (3, 3) {
+            return None;
+        }
+        let row = (digit - 1) / 3;
+        let row = if numpad { 2 - row } else { row };
+        Some(self.rules.cell((digit - 1) % 3, row))
     }
-    fn center_row(&self, mouse_y: f32) -> bool {
-        !self.top_row(mouse_y) && !self.bottom_row(mouse_y)
+    /// Moves the keyboard cursor by a step, staying on the board. It starts
+    /// in the middle.
+    pub fn move_cursor(&mut self, columns: isize, rows: isize) {
+        let (width, height) = (self.rules.width as isize, self.rules.height as isize);
+        let cell = match self.cursor {
+            Some(cell) => cell,
+            None => {
+                self.cursor =
+                    Some(self.rules.cell(width as usize / 2, height as usize / 2));
+                return;
+            }
+        };
+        let column = (self.rules.column(cell) as isize + columns)
+            .max(0)
+            .min(width - 1);
+        let row = (self.rules.row(cell) as isize + rows)
+            .max(0)
+            .min(height - 1);
+        self.cursor = Some(self.rules.cell(column as usize, row as usize));
     }
-    pub fn register_click(&mut self, app: &App) {
+    /// Plays the move `cell` completes, or starts over once the game is done.
+    pub fn register_cell(&mut self, cell: Option<usize>) {
         match self.state {
             BoardState::InGame => {
-                let (x, y) = (app.mouse.position().x, app.mouse.position().y);
-                let location = match (x, y) {
-                    (x, y) if self.left_column(x) && self.top_row(y) => 0,
-                    (x, y) if self.center_column(x) && self.top_row(y) => 1,
-                    (x, y) if self.right_column(x) && self.top_row(y) => 2,
-
-                    (x, y) if self.left_column(x) && self.center_row(y) => 3,
-                    (x, y) if self.center_column(x) && self.center_row(y) => 4,
-                    (x, y) if self.right_column(x) && self.center_row(y) => 5,
-
-                    (x, y) if self.left_column(x) && self.bottom_row(y) => 6,
-                    (x, y) if self.center_column(x) && self.bottom_row(y) => 7,
-                    (x, y) if self.right_column(x) && self.bottom_row(y) => 8,
-
-                    _ => unreachable!(),
-                };
-                if self.board[location] != Field::Empty {
-                    return;
-                }
-                match self.current_player {
-                    Player::Player1 => self.board[location] = Field::X,
-                    Player::Player2 => self.board[location] = Field::O,
+                if let Some(play) = cell.and_then(|cell| self.move_to(cell)) {
+                    self.play(play);
                 }
-                self.made_move();
             }
             _ => {
-                let new_self = Self::new(self.rect, PlayerMode::PlayUndecided);
+                let new_self =
+                    Self::new(self.rect, PlayerMode::PlayUndecided, self.rules)
+                        .with_setup(self.setup);
                 std::mem::replace(self, new_self);
             }
         };
     }
+    /// The first click picks up one of your own pieces, the second puts it
+    /// down on an adjacent empty cell.
+    fn register_slide(&mut self, clicked: usize) -> Option<Move> {
+        if self.board[clicked] == self.current_player.get_sigil() {
+            self.selected = Some(clicked);
+            return None;
+        }
+        let play = Move::Slide(self.selected?, clicked);
+        if self
+            .rules
+            .legal_moves(&self.board, self.current_player)
+            .contains(&play)
+        {
+            Some(play)
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::rules::Variant;
+
+    /// An empty board to play `rules` on, for the tests of every module.
+    #[allow(dead_code)]
+    pub(crate) fn board(rules: Rules) -> Board {
+        Board::new(Rect::from_w_h(300.0, 300.0), PlayerMode::PlayX, rules)
+    }
+
+    #[test]
+    fn either_side_can_move_first_on_an_even_board() {
+        let mut even = board(Rules::classic()).with_first(Player::Player2);
+        assert_eq!(even.current_player, Player::Player2);
+        even.play(Move::Place(4));
+        assert_eq!(even.board[4], Field::O);
+        assert_eq!(even.first_player(), Player::Player2);
+        // the centre is already taken by X, so O goes first whatever is asked
+        let uneven = board(Rules::classic())
+            .with_setup(Setup::Preset(2))
+            .with_first(Player::Player1);
+        assert_eq!(uneven.current_player, Player::Player2);
+    }
+    #[test]
+    fn number_keys_pick_cells() {
+        let classic = board(Rules::classic());
+        assert_eq!(classic.digit_cell(1, false), Some(0));
+        assert_eq!(classic.digit_cell(6, false), Some(5));
+        assert_eq!(classic.digit_cell(1, true), Some(6));
+        assert_eq!(classic.digit_cell(9, true), Some(2));
+        assert_eq!(classic.digit_cell(0, false), None);
+        let connect_four = board(Variant::ConnectFour.rules());
+        assert_eq!(connect_four.digit_cell(7, false), Some(6));
+        assert_eq!(connect_four.digit_cell(8, false), None);
+    }
+    #[test]
+    fn the_cursor_starts_in_the_middle_and_stays_on_the_board() {
+        let mut board = board(Rules::classic());
+        board.move_cursor(1, 0);
+        assert_eq!(board.cursor, Some(4));
+        board.move_cursor(0, -1);
+        board.move_cursor(0, -1);
+        assert_eq!(board.cursor, Some(1));
+        board.move_cursor(5, 0);
+        assert_eq!(board.cursor, Some(2));
+        board.register_cell(board.cursor);
+        assert_eq!(board.board[2], Field::X);
+    }
 }
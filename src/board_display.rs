@@ -1,200 +1,362 @@
+use crate::animation::Animation;
 use crate::board::{Board, BoardState};
 use crate::field::Field;
+use crate::hex;
 use crate::player::Player;
+use crate::theme::BoardStyle;
 use nannou::prelude::*;
 
 impl Board {
-    pub fn show_selections(&self, draw: &app::Draw, rect: &Rect) {
-        let dims = (rect.right() - rect.left()) / 3.0;
-        let draw_text = |sigil: &str, location: &Rect| {
-            let text = text(sigil).font_size(dims as u32).build(*location);
-            draw.path().fill().color(BLACK).events(text.path_events());
+    pub(crate) fn cell_size(&self, rect: &Rect) -> (f32, f32) {
+        if self.rules.hex {
+            return hex::cell_size(hex::size(rect, &self.rules));
+        }
+        (
+            rect.w() / self.rules.width as f32,
+            rect.h() / self.rules.height as f32,
+        )
+    }
+    pub(crate) fn cell_center(&self, rect: &Rect, cell: usize) -> Point2 {
+        if self.rules.hex {
+            return hex::center(rect, &self.rules, cell);
+        }
+        let (cell_width, cell_height) = self.cell_size(rect);
+        pt2(
+            rect.left() + (self.rules.column(cell) as f32 + 0.5) * cell_width,
+            rect.top() - (self.rules.row(cell) as f32 + 0.5) * cell_height,
+        )
+    }
+    /// Colours in a whole cell, square or hexagonal.
+    fn fill_cell(&self, draw: &app::Draw, rect: &Rect, cell: usize, color: Srgb<u8>) {
+        let center = self.cell_center(rect, cell);
+        if self.rules.hex {
+            let corners = hex::corners(center, hex::size(rect, &self.rules));
+            draw.polygon().color(color).points(corners);
+        } else {
+            let (cell_width, cell_height) = self.cell_size(rect);
+            draw.rect()
+                .xy(center)
+                .w_h(cell_width, cell_height)
+                .color(color);
+        }
+    }
+    pub fn show_grid(&self, draw: &app::Draw, rect: &Rect, style: &BoardStyle) {
+        let (color, weight) = (style.grid.0, style.grid_weight);
+        if self.rules.hex {
+            let size = hex::size(rect, &self.rules);
+            (0..self.rules.size()).for_each(|cell| {
+                draw.path()
+                    .stroke()
+                    .weight(weight)
+                    .color(color)
+                    .points_closed(hex::corners(self.cell_center(rect, cell), size));
+            });
+            return;
+        }
+        let (cell_width, cell_height) = self.cell_size(rect);
+        // vertical lines
+        (1..self.rules.width).for_each(|column| {
+            let x = rect.left() + column as f32 * cell_width;
+            draw.line()
+                .start(pt2(x, rect.top()))
+                .end(pt2(x, rect.bottom()))
+                .stroke_weight(weight)
+                .color(color);
+        });
+        // horizontal lines
+        (1..self.rules.height).for_each(|row| {
+            let y = rect.top() - row as f32 * cell_height;
+            draw.line()
+                .start(pt2(rect.left(), y))
+                .end(pt2(rect.right(), y))
+                .stroke_weight(weight)
+                .color(color);
+        });
+    }
+    fn show_selected(&self, draw: &app::Draw, rect: &Rect, style: &BoardStyle) {
+        if let Some(cell) = self.cursor {
+            self.fill_cell(draw, rect, cell, style.cursor.0);
+        }
+        if let Some(cell) = self.selected {
+            self.fill_cell(draw, rect, cell, style.selected.0);
+        }
+    }
+    pub fn show_selections(
+        &self,
+        draw: &app::Draw,
+        rect: &Rect,
+        style: &BoardStyle,
+        animation: &Animation,
+    ) {
+        let (cell_width, cell_height) = self.cell_size(rect);
+        let dims = cell_width.min(cell_height);
+        let draw_text = |field: Field, location: &Rect, faded: bool| {
+            let text = text(&field.to_string())
+                .font_size(dims as u32)
+                .build(*location);
+            if faded {
+                draw.path()
+                    .fill()
+                    .color(style.faded_mark(field))
+                    .events(text.path_events());
+            } else {
+                draw.path()
+                    .fill()
+                    .color(style.mark(field))
+                    .events(text.path_events());
+            }
         };
+        let vanishing = self.vanishing();
         self.board.iter().enumerate().for_each(|(i, v)| {
-            if *v != Field::Empty {
-                match (i, v) {
-                    (0, v) => {
-                        let location = Rect::from_x_y_w_h(
-                            rect.left() + dims / 2.0,
-                            rect.top() - dims / 3.0,
-                            dims,
-                            dims,
-                        );
-                        draw_text(&v.to_string(), &location);
-                    }
-                    (1, v) => {
-                        let location = Rect::from_x_y_w_h(
-                            rect.right() / 3.0 - dims / 2.0,
-                            rect.top() - dims / 3.0,
-                            dims,
-                            dims,
-                        );
-                        draw_text(&v.to_string(), &location);
-                    }
-                    (2, v) => {
-                        let location = Rect::from_x_y_w_h(
-                            rect.right() - dims / 2.0,
-                            rect.top() - dims / 3.0,
-                            dims,
-                            dims,
-                        );
-                        draw_text(&v.to_string(), &location);
-                    }
-                    (3, v) => {
-                        let location = Rect::from_x_y_w_h(
-                            rect.left() + dims / 2.0,
-                            rect.top() / 3.0 - dims / 3.0,
-                            dims,
-                            dims,
-                        );
-                        draw_text(&v.to_string(), &location);
-                    }
-                    (4, v) => {
-                        let location = Rect::from_x_y_w_h(
-                            rect.left() / 3.0 + dims / 2.0,
-                            rect.top() / 3.0 - dims / 3.0,
-                            dims,
-                            dims,
-                        );
-                        draw_text(&v.to_string(), &location);
-                    }
-                    (5, v) => {
-                        let location = Rect::from_x_y_w_h(
-                            rect.right() - dims / 2.0,
-                            rect.top() / 3.0 - dims / 3.0,
-                            dims,
-                            dims,
-                        );
-                        draw_text(&v.to_string(), &location);
-                    }
-                    (6, v) => {
-                        let location = Rect::from_x_y_w_h(
-                            rect.left() + dims / 2.0,
-                            rect.bottom() / 3.0 - dims / 3.0,
-                            dims,
-                            dims,
-                        );
-                        draw_text(&v.to_string(), &location);
-                    }
-                    (7, v) => {
-                        let location = Rect::from_x_y_w_h(
-                            rect.right() / 3.0 - dims / 2.0,
-                            rect.bottom() / 3.0 - dims / 3.0,
-                            dims,
-                            dims,
-                        );
-                        draw_text(&v.to_string(), &location);
-                    }
-                    (8, v) => {
-                        let location = Rect::from_x_y_w_h(
-                            rect.right() - dims / 2.0,
-                            rect.bottom() / 3.0 - dims / 3.0,
-                            dims,
-                            dims,
-                        );
-                        draw_text(&v.to_string(), &location);
-                    }
-                    _ => unimplemented!(),
-                };
+            if *v == Field::Blocked {
+                self.fill_cell(draw, rect, i, style.blocked.0);
+            } else if *v != Field::Empty {
+                // glyphs sit a little low in their box, so nudge them up
+                let center = self.cell_center(rect, i);
+                let location =
+                    Rect::from_x_y_w_h(center.x, center.y + dims / 6.0, dims, dims);
+                let drawn = animation.mark(i);
+                if drawn < 1.0 {
+                    self.show_strokes(draw, center, dims, *v, drawn, style);
+                } else {
+                    draw_text(*v, &location, vanishing.contains(&i));
+                }
+                if style.shapes {
+                    self.show_cue(draw, center, dims, *v, style);
+                }
             }
         });
     }
-    fn show_winner(&self, draw: &app::Draw, rect: &Rect, win: (usize, usize, usize)) {
-        let start_x;
-        let start_y;
-        let end_x;
-        let end_y;
-        let (top, _, bottom) = win;
-        match (top, bottom) {
-            // top row
-            (0, 2) => {
-                start_x = rect.left();
-                start_y = rect.top() - rect.top() / 3.0;
-                end_x = rect.right();
-                end_y = rect.top() - rect.top() / 3.0;
-            }
-            // left column
-            (0, 6) => {
-                start_x = rect.left() - rect.left() / 3.0;
-                start_y = rect.top();
-                end_x = rect.left() - rect.left() / 3.0;
-                end_y = rect.bottom();
-            }
-            // middle row
-            (3, 5) => {
-                start_x = rect.left();
-                start_y = 0.0;
-                end_x = rect.right();
-                end_y = 0.0;
-            }
-            // middle column
-            (1, 7) => {
-                start_x = 0.0;
-                start_y = rect.top();
-                end_x = 0.0;
-                end_y = rect.bottom();
-            }
-            // bottom row
-            (6, 8) => {
-                start_x = rect.left();
-                start_y = rect.bottom() - rect.bottom() / 3.0;
-                end_x = rect.right();
-                end_y = rect.bottom() - rect.bottom() / 3.0;
-            }
-            // right column
-            (2, 8) => {
-                start_x = rect.right() - rect.right() / 3.0;
-                start_y = rect.top();
-                end_x = rect.right() - rect.right() / 3.0;
-                end_y = rect.bottom();
+    /// Marks that go away on their owner's next placement.
+    pub(crate) fn vanishing(&self) -> Vec<usize> {
+        self.queues
+            .iter()
+            .filter_map(|queue| self.rules.vanishing(queue))
+            .collect()
+    }
+    /// The part of a mark drawn so far, as `strokes` lays it out.
+    fn show_strokes(
+        &self,
+        draw: &app::Draw,
+        center: Point2,
+        dims: f32,
+        field: Field,
+        drawn: f32,
+        style: &BoardStyle,
+    ) {
+        let weight = dims / 12.0;
+        let points = strokes(center, dims, field, drawn);
+        for stroke in points {
+            draw.path()
+                .stroke()
+                .weight(weight)
+                .color(style.mark(field))
+                .points(stroke);
+        }
+    }
+    /// Frames a mark in a shape of its own, a diamond around X and a square
+    /// around O, so the sides differ in more than their glyphs.
+    fn show_cue(
+        &self,
+        draw: &app::Draw,
+        center: Point2,
+        dims: f32,
+        field: Field,
+        style: &BoardStyle,
+    ) {
+        let half = dims * 0.42;
+        let corners = match field {
+            Field::X => vec![
+                pt2(center.x, center.y + half),
+                pt2(center.x + half, center.y),
+                pt2(center.x, center.y - half),
+                pt2(center.x - half, center.y),
+            ],
+            _ => {
+                let half = half * 0.8;
+                vec![
+                    pt2(center.x - half, center.y + half),
+                    pt2(center.x + half, center.y + half),
+                    pt2(center.x + half, center.y - half),
+                    pt2(center.x - half, center.y - half),
+                ]
             }
-            // left diag
-            (0, 8) => {
-                start_x = rect.left();
-                start_y = rect.top();
-                end_x = rect.right();
-                end_y = rect.bottom();
+        };
+        draw.path()
+            .stroke()
+            .weight(3.0)
+            .color(style.mark(field))
+            .points_closed(corners);
+    }
+    /// Where the line through the winning cells `win` runs, as the ends of
+    /// its segments. A wrapped line on a torus has one segment per stretch
+    /// that runs between two edges of the board.
+    pub(crate) fn winner_segments(
+        &self,
+        rect: &Rect,
+        win: &[usize],
+    ) -> Vec<(Point2, Point2)> {
+        let (cell_width, cell_height) = self.cell_size(rect);
+        let (dx, dy) = self.rules.step(win[0], win[1]);
+        // run each segment half a cell past its end cells
+        let half_step = if self.rules.hex {
+            let size = hex::size(rect, &self.rules);
+            hex::offset(size, dx as f32 / 2.0, dy as f32 / 2.0)
+        } else {
+            vec2(dx as f32 * cell_width / 2.0, -dy as f32 * cell_height / 2.0)
+        };
+        let mut segments: Vec<Vec<usize>> = vec![vec![win[0]]];
+        win.windows(2).for_each(|pair| {
+            let column_step = self.rules.column(pair[1]) as isize
+                - self.rules.column(pair[0]) as isize;
+            let row_step =
+                self.rules.row(pair[1]) as isize - self.rules.row(pair[0]) as isize;
+            if (column_step, row_step) == (dx, dy) {
+                segments.last_mut().unwrap().push(pair[1]);
+            } else {
+                segments.push(vec![pair[1]]);
             }
-            // right diag
-            (2, 6) => {
-                start_x = rect.right();
-                start_y = rect.top();
-                end_x = rect.left();
-                end_y = rect.bottom();
+        });
+        segments
+            .iter()
+            .map(|segment| {
+                let first = self.cell_center(rect, segment[0]);
+                let last = self.cell_center(rect, segment[segment.len() - 1]);
+                (first - half_step, last + half_step)
+            })
+            .collect()
+    }
+    /// Draws the winning line, only the first `drawn` of its length so it
+    /// can sweep across.
+    fn show_winner(
+        &self,
+        draw: &app::Draw,
+        rect: &Rect,
+        win: &[usize],
+        style: &BoardStyle,
+        drawn: f32,
+    ) {
+        let ends = self.winner_segments(rect, win);
+        let total: f32 = ends
+            .iter()
+            .map(|(start, end)| (*end - *start).magnitude())
+            .sum();
+        let mut left = total * drawn;
+        for (start, end) in ends {
+            let length = (end - start).magnitude();
+            if left <= 0.0 || length == 0.0 {
+                break;
             }
-            _ => unreachable!(),
+            let part = (left / length).min(1.0);
+            draw.line()
+                .start(start)
+                .end(start + (end - start) * part)
+                .stroke_weight(style.line_weight)
+                .color(style.line.0);
+            left -= length;
         }
-        draw.line()
-            .start(pt2(start_x, start_y))
-            .end(pt2(end_x, end_y))
-            .stroke_weight(2.0)
-            .color(BLACK);
     }
-    pub fn display(&self, draw: &app::Draw, rect: &Rect) {
+    /// Draws the board in `rect`, in the colors and weights of `style`, as
+    /// far along as `animation` has got.
+    pub fn display(
+        &self,
+        draw: &app::Draw,
+        rect: &Rect,
+        style: &BoardStyle,
+        animation: &Animation,
+    ) {
+        self.show_grid(draw, rect, style);
+        // "X Wins!" fills most of the width of a 600 pixel board
+        let result_size = (rect.w().min(rect.h()) / 8.0) as u32;
         match &self.state {
             BoardState::Tie => {
-                self.show_selections(draw, rect);
+                self.show_selections(draw, rect, style, animation);
                 let location = rect.pad(20.0);
                 let wins = "Tie!".to_string();
-                let text = text(&wins).font_size(75).build(location);
-                draw.path().fill().color(WHITE).events(text.path_events());
+                let text = text(&wins).font_size(result_size).build(location);
+                draw.path()
+                    .fill()
+                    .color(style.result.0)
+                    .events(text.path_events());
             }
             BoardState::InGame => {
-                self.show_selections(draw, rect);
+                self.show_selected(draw, rect, style);
+                self.show_selections(draw, rect, style, animation);
             }
             winner => {
-                self.show_selections(draw, rect);
+                self.show_selections(draw, rect, style, animation);
                 let (winning_player, winning_pos) = match winner {
                     BoardState::Winner(Player::Player1, x) => (Field::X, x),
                     BoardState::Winner(Player::Player2, x) => (Field::O, x),
                     _ => unreachable!(),
                 };
-                self.show_winner(draw, rect, *winning_pos);
+                // a game won on time has no line to show
+                if winning_pos.len() > 1 {
+                    self.show_winner(draw, rect, winning_pos, style, animation.line());
+                }
                 let location = rect.pad(20.0);
                 let wins = format!("{} Wins!", &winning_player.to_string());
-                let text = text(&wins).font_size(75).build(location);
-                draw.path().fill().color(WHITE).events(text.path_events());
+                let text = text(&wins).font_size(result_size).build(location);
+                draw.path()
+                    .fill()
+                    .color(style.result.0)
+                    .events(text.path_events());
             }
         }
     }
 }
+
+/// The lines of a mark `drawn` of the way through being drawn: X's two
+/// strokes one after the other, or O's circle going round from the top.
+/// Nothing is drawn before it starts.
+fn strokes(center: Point2, dims: f32, field: Field, drawn: f32) -> Vec<Vec<Point2>> {
+    if drawn <= 0.0 {
+        return vec![];
+    }
+    let half = dims * 0.3;
+    match field {
+        Field::X => {
+            let strokes = [
+                (pt2(-half, half), pt2(half, -half)),
+                (pt2(half, half), pt2(-half, -half)),
+            ];
+            strokes
+                .iter()
+                .enumerate()
+                .map(|(i, &(start, end))| {
+                    let part = (drawn * 2.0 - i as f32).clamp(0.0, 1.0);
+                    vec![center + start, center + start + (end - start) * part]
+                })
+                .collect()
+        }
+        _ => {
+            let steps = (drawn * 48.0).ceil() as usize;
+            let arc = (0..=steps)
+                .map(|step| {
+                    let angle = PI / 2.0 + 2.0 * PI * drawn * step as f32 / steps as f32;
+                    center + vec2(angle.cos(), angle.sin()) * half
+                })
+                .collect();
+            vec![arc]
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn marks_not_yet_started_draw_nothing() {
+        for &field in &[Field::X, Field::O] {
+            assert!(strokes(pt2(0.0, 0.0), 100.0, field, 0.0).is_empty());
+        }
+        // the first sliver of a circle is a real point, not a division by zero
+        let arc = &strokes(pt2(0.0, 0.0), 100.0, Field::O, 0.001)[0];
+        assert_eq!(arc.len(), 2);
+        assert!(arc
+            .iter()
+            .all(|point| point.x.is_finite() && point.y.is_finite()));
+        assert!(arc[0].x.abs() < 1e-3 && (arc[0].y - 30.0).abs() < 1e-3);
+    }
+}
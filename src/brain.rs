@@ -1,84 +1,340 @@
-use crate::board::BoardState;
+use crate::board::{Board, BoardState};
 use crate::eval::Eval;
 use crate::field::Field;
 use crate::player::Player;
+use crate::rules::{Move, Rules};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
 
-pub fn minimax(state: BoardState, board: &[Field], player: Player, depth: i64) -> Eval {
-    match state {
-        BoardState::Tie => Eval {
-            position: 0,
-            score: 0,
-        },
-        BoardState::InGame => {
-            let evaluated_moves: Vec<Eval> = board
-                .iter()
-                .enumerate()
-                .filter_map(|(i, v)| match v {
-                    Field::Empty => {
-                        let mut cloned_board = Vec::from(board).clone();
-                        let new_field = match player {
-                            Player::Player1 => Field::X,
-                            Player::Player2 => Field::O,
-                        };
-                        cloned_board[i] = new_field;
-                        let score = minimax(
-                            check_winner(&cloned_board),
-                            &cloned_board,
-                            -player,
-                            depth + 1,
-                        )
-                        .score;
-                        Some(Eval::new(i, score))
+/// Score of a win on the very first ply; quicker wins score higher.
+const WIN: i64 = 10_000;
+
+/// Best move in a bare position, as if every mark on the board was placed in
+/// index order and no position had come up before.
+pub fn minimax(
+    state: &BoardState,
+    board: &[Field],
+    rules: &Rules,
+    player: Player,
+    depth: i64,
+) -> Eval {
+    let queues = initial_queues(board);
+    let seen = vec![position_key(board, &queues, player)];
+    Search::new(rules, board, queues, &seen).run(state, player, depth)
+}
+
+/// Every move `player` has in a bare position, as for `minimax`, with its
+/// score, best first.
+pub fn rank_moves(board: &[Field], rules: &Rules, player: Player) -> Vec<Eval> {
+    if check_winner(board, rules) != BoardState::InGame {
+        return vec![];
+    }
+    let queues = initial_queues(board);
+    let seen = vec![position_key(board, &queues, player)];
+    let mut ranking: Vec<Eval> = rules
+        .legal_moves(board, player)
+        .into_iter()
+        .map(|play| {
+            let mut search = Search::new(rules, board, queues.clone(), &seen);
+            let queue = &mut search.queues[player.index()];
+            rules.make_move(&mut search.board, queue, play, player.get_sigil());
+            let state = check_lines(&search.board, &search.lines);
+            // the reply is one ply in, so scores match the ones `minimax` sees
+            Eval::new(play, search.run(&state, -player, 1).score)
+        })
+        .collect();
+    match player {
+        Player::Player1 => ranking.sort(),
+        Player::Player2 => ranking.sort_by(|a, b| b.cmp(a)),
+    }
+    ranking
+}
+
+/// The side a score says wins with best play, if it is sure of one.
+pub fn forced_winner(score: i64) -> Option<Player> {
+    if score < -WIN / 2 {
+        Some(Player::Player1)
+    } else if score > WIN / 2 {
+        Some(Player::Player2)
+    } else {
+        None
+    }
+}
+
+/// How hard the computer tries.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::Hard
+    }
+}
+
+impl Difficulty {
+    /// How many plies the computer looks ahead under `rules`.
+    pub fn search_depth(self, rules: &Rules) -> i64 {
+        match self {
+            Difficulty::Easy => 1,
+            Difficulty::Medium => (rules.search_depth / 2).max(2),
+            Difficulty::Hard => rules.search_depth,
+        }
+    }
+
+    /// The difficulty shown after this one when cycling through the menu.
+    pub fn next(self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Medium,
+            Difficulty::Medium => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+}
+
+impl std::string::ToString for Difficulty {
+    fn to_string(&self) -> String {
+        match self {
+            Difficulty::Easy => "Easy".to_string(),
+            Difficulty::Medium => "Medium".to_string(),
+            Difficulty::Hard => "Hard".to_string(),
+        }
+    }
+}
+
+/// Best move for the player to move in a game in progress.
+pub fn best_move(game: &Board) -> Eval {
+    best_move_at(game, Difficulty::Hard)
+}
+
+/// The move the computer picks at the given difficulty.
+pub fn best_move_at(game: &Board, difficulty: Difficulty) -> Eval {
+    let rules = Rules {
+        search_depth: difficulty.search_depth(&game.rules),
+        ..game.rules
+    };
+    Search::new(&rules, &game.board, game.queues.clone(), &game.history).run(
+        &game.state,
+        game.current_player,
+        0,
+    )
+}
+
+/// Each player's marks in index order, for positions that didn't come about
+/// move by move.
+pub fn initial_queues(board: &[Field]) -> [VecDeque<usize>; 2] {
+    let mut queues = [VecDeque::new(), VecDeque::new()];
+    board.iter().enumerate().for_each(|(i, v)| match v {
+        Field::X => queues[Player::Player1.index()].push_back(i),
+        Field::O => queues[Player::Player2.index()].push_back(i),
+        Field::Empty | Field::Blocked => {}
+    });
+    queues
+}
+
+/// Identifies a position for repetition checks: the marks, the order they
+/// will vanish in, and the player to move.
+pub fn position_key(
+    board: &[Field],
+    queues: &[VecDeque<usize>; 2],
+    player: Player,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    board.hash(&mut hasher);
+    queues.hash(&mut hasher);
+    player.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Alpha-beta search state. Player1 minimizes the score and Player2 maximizes it.
+struct Search<'a> {
+    rules: &'a Rules,
+    lines: Vec<Vec<usize>>,
+    board: Vec<Field>,
+    queues: [VecDeque<usize>; 2],
+    /// Repetition table: positions from the game so far and from the line
+    /// currently searched. Coming back to one of them is scored as a draw,
+    /// which keeps games that can loop from searching in circles.
+    seen: HashSet<u64>,
+    /// Depth of the current iterative deepening pass.
+    limit: i64,
+    /// Best root move of the previous pass, tried first in the next one.
+    principal: Option<Move>,
+}
+
+impl<'a> Search<'a> {
+    fn new(
+        rules: &'a Rules,
+        board: &[Field],
+        queues: [VecDeque<usize>; 2],
+        history: &[u64],
+    ) -> Self {
+        // blocked cells never clear, so lines through them can be dropped
+        let lines = rules
+            .winning_lines()
+            .into_iter()
+            .filter(|line| line.iter().all(|&i| board[i] != Field::Blocked))
+            .collect();
+        Search {
+            rules,
+            lines,
+            board: Vec::from(board),
+            queues,
+            seen: history.iter().copied().collect(),
+            limit: 0,
+            principal: None,
+        }
+    }
+
+    /// Iterative deepening: search one ply deeper each pass until the depth
+    /// limit of the rules, or until a forced result turns up.
+    fn run(&mut self, state: &BoardState, player: Player, depth: i64) -> Eval {
+        let mut best: Option<Eval> = None;
+        for limit in depth + 1..=self.rules.search_depth.max(depth + 1) {
+            self.limit = limit;
+            self.principal = best.map(|eval| eval.play);
+            let eval = self.search(state, player, depth, -WIN - 1, WIN + 1);
+            best = Some(eval);
+            if eval.score.abs() > WIN / 2 {
+                break;
+            }
+        }
+        best.unwrap()
+    }
+
+    fn search(
+        &mut self,
+        state: &BoardState,
+        player: Player,
+        depth: i64,
+        mut alpha: i64,
+        mut beta: i64,
+    ) -> Eval {
+        let no_move = Move::Place(0);
+        match state {
+            BoardState::Tie => Eval::new(no_move, 0),
+            BoardState::InGame if depth >= self.limit => {
+                Eval::new(no_move, estimate(&self.board, &self.lines))
+            }
+            BoardState::InGame => {
+                let mut moves = self.rules.legal_moves(&self.board, player);
+                if let Some(principal) = self.principal.take() {
+                    if let Some(i) = moves.iter().position(|&play| play == principal) {
+                        moves[..=i].rotate_right(1);
                     }
-                    _ => None,
-                })
-                .collect();
-            let mut cloned_evals = evaluated_moves.clone();
-            cloned_evals.sort();
-            match player {
-                Player::Player1 => {
-                    let first = cloned_evals.first();
-                    *first.unwrap()
                 }
-                Player::Player2 => {
-                    let last = cloned_evals.last();
-                    *last.unwrap()
+                let mut best: Option<Eval> = None;
+                for play in moves {
+                    let sigil = player.get_sigil();
+                    let queue = &mut self.queues[player.index()];
+                    let vanished =
+                        self.rules.make_move(&mut self.board, queue, play, sigil);
+                    let score = if !self.rules.can_repeat() {
+                        let state = check_lines(&self.board, &self.lines);
+                        self.search(&state, -player, depth + 1, alpha, beta).score
+                    } else {
+                        let key = position_key(&self.board, &self.queues, -player);
+                        if self.seen.contains(&key) {
+                            0
+                        } else {
+                            self.seen.insert(key);
+                            let state = check_lines(&self.board, &self.lines);
+                            let score = self
+                                .search(&state, -player, depth + 1, alpha, beta)
+                                .score;
+                            self.seen.remove(&key);
+                            score
+                        }
+                    };
+                    let queue = &mut self.queues[player.index()];
+                    self.rules.unmake_move(
+                        &mut self.board,
+                        queue,
+                        play,
+                        sigil,
+                        vanished,
+                    );
+                    let better = match (player, best) {
+                        (_, None) => true,
+                        (Player::Player1, Some(eval)) => score < eval.score,
+                        (Player::Player2, Some(eval)) => score > eval.score,
+                    };
+                    if better {
+                        best = Some(Eval::new(play, score));
+                    }
+                    match player {
+                        Player::Player1 => beta = beta.min(score),
+                        Player::Player2 => alpha = alpha.max(score),
+                    }
+                    if alpha >= beta {
+                        break;
+                    }
                 }
+                // a side with nothing to play is stuck, which counts as a draw
+                best.unwrap_or_else(|| Eval::new(no_move, 0))
             }
-        }
-        winner => match winner {
-            BoardState::Winner(Player::Player1, _) => Eval {
-                position: 0,
-                score: depth - 10,
-            },
-            BoardState::Winner(Player::Player2, _x) => Eval {
-                position: 0,
-                score: 10 - depth,
+            winner => match winner {
+                BoardState::Winner(Player::Player1, _) => {
+                    Eval::new(no_move, depth - WIN)
+                }
+                BoardState::Winner(Player::Player2, _x) => {
+                    Eval::new(no_move, WIN - depth)
+                }
+                _ => unreachable!(),
             },
-            _ => unreachable!(),
-        },
+        }
+    }
+}
+
+/// Rough value of an unfinished position: lines still open to only one player
+/// count for that player, more so the fuller they are.
+fn estimate(board: &[Field], lines: &[Vec<usize>]) -> i64 {
+    lines
+        .iter()
+        .map(|line| {
+            let xs = line.iter().filter(|&&i| board[i] == Field::X).count() as i64;
+            let os = line.iter().filter(|&&i| board[i] == Field::O).count() as i64;
+            match (xs, os) {
+                (0, os) => os * os,
+                (xs, 0) => -xs * xs,
+                _ => 0,
+            }
+        })
+        .sum()
+}
+
+pub fn check_winner(board: &[Field], rules: &Rules) -> BoardState {
+    check_lines(board, &rules.winning_lines())
+}
+
+/// How often the latest position in `history` has come up, itself included.
+pub fn repetitions(history: &[u64]) -> usize {
+    match history.last() {
+        Some(latest) => history
+            .iter()
+            .filter(|&position| position == latest)
+            .count(),
+        None => 0,
     }
 }
-pub fn check_winner(board: &[Field]) -> BoardState {
-    let winning_boards = vec![
-        vec![0, 1, 2],
-        vec![3, 4, 5],
-        vec![6, 7, 8],
-        vec![0, 3, 6],
-        vec![1, 4, 7],
-        vec![2, 5, 8],
-        vec![0, 4, 8],
-        vec![2, 4, 6],
-    ];
+
+fn check_lines(board: &[Field], lines: &[Vec<usize>]) -> BoardState {
     let mut winning = None;
-    winning_boards.iter().any(|ts| {
-        if board[ts[0]] == board[ts[1]] && board[ts[1]] == board[ts[2]] {
-            if board[ts[0]] == Field::X {
-                winning = Some(BoardState::Winner(Player::Player1, (ts[0], ts[1], ts[2])));
+    // blocked cells belong to nobody, so they never complete a line
+    lines.iter().any(|ts| {
+        let first = board[ts[0]];
+        if ts.iter().all(|&i| board[i] == first) {
+            if first == Field::X {
+                winning = Some(BoardState::Winner(Player::Player1, ts.clone()));
                 return true;
-            } else if board[ts[0]] == Field::O {
-                winning =
-                    Some(BoardState::Winner(Player::Player2, (ts[0], ts[1], ts[2])));
+            } else if first == Field::O {
+                winning = Some(BoardState::Winner(Player::Player2, ts.clone()));
                 return true;
             }
         }
@@ -97,6 +353,10 @@ mod test {
     #[allow(unused_imports)]
     use crate::board::Board;
     #[allow(unused_imports)]
+    use crate::model::PlayerMode;
+    #[allow(unused_imports)]
+    use crate::setup::Setup;
+    #[allow(unused_imports)]
     use nannou::geom::Rect;
     #[test]
     fn top_row_human_win() {
@@ -112,10 +372,10 @@ mod test {
             Field::Empty,
             Field::Empty,
         ];
-        let actual = check_winner(board);
+        let actual = check_winner(board, &Rules::classic());
         assert_eq!(
             actual,
-            BoardState::Winner(Player::Player1, (0, 1, 2)),
+            BoardState::Winner(Player::Player1, vec![0, 1, 2]),
             "Player 1 Win"
         );
     }
@@ -132,10 +392,10 @@ mod test {
             Field::Empty,
             Field::Empty,
         ];
-        let actual = check_winner(board);
+        let actual = check_winner(board, &Rules::classic());
         assert_eq!(
             actual,
-            BoardState::Winner(Player::Player1, (3, 4, 5)),
+            BoardState::Winner(Player::Player1, vec![3, 4, 5]),
             "Player 1 Win"
         );
     }
@@ -152,10 +412,10 @@ mod test {
             Field::X,
             Field::X,
         ];
-        let actual = check_winner(board);
+        let actual = check_winner(board, &Rules::classic());
         assert_eq!(
             actual,
-            BoardState::Winner(Player::Player1, (6, 7, 8)),
+            BoardState::Winner(Player::Player1, vec![6, 7, 8]),
             "Player1 Win"
         );
     }
@@ -173,10 +433,10 @@ mod test {
             Field::Empty,
             Field::Empty,
         ];
-        let actual = check_winner(board);
+        let actual = check_winner(board, &Rules::classic());
         assert_eq!(
             actual,
-            BoardState::Winner(Player::Player2, (0, 1, 2)),
+            BoardState::Winner(Player::Player2, vec![0, 1, 2]),
             "Player2 Win"
         );
     }
@@ -193,10 +453,10 @@ mod test {
             Field::Empty,
             Field::Empty,
         ];
-        let actual = check_winner(board);
+        let actual = check_winner(board, &Rules::classic());
         assert_eq!(
             actual,
-            BoardState::Winner(Player::Player2, (3, 4, 5)),
+            BoardState::Winner(Player::Player2, vec![3, 4, 5]),
             "Player2 Win"
         );
     }
@@ -213,10 +473,10 @@ mod test {
             Field::O,
             Field::O,
         ];
-        let actual = check_winner(board);
+        let actual = check_winner(board, &Rules::classic());
         assert_eq!(
             actual,
-            BoardState::Winner(Player::Player2, (6, 7, 8)),
+            BoardState::Winner(Player::Player2, vec![6, 7, 8]),
             "Player2 Win"
         );
     }
@@ -233,8 +493,8 @@ mod test {
             Field::Empty,
             Field::Empty,
         ];
-        let actual = check_winner(board);
-        assert_eq!(actual, BoardState::Winner(Player::Player1, (0, 3, 6)),);
+        let actual = check_winner(board, &Rules::classic());
+        assert_eq!(actual, BoardState::Winner(Player::Player1, vec![0, 3, 6]),);
     }
     #[test]
     fn mid_col_human_win() {
@@ -249,8 +509,8 @@ mod test {
             Field::X,
             Field::Empty,
         ];
-        let actual = check_winner(board);
-        assert_eq!(actual, BoardState::Winner(Player::Player1, (1, 4, 7)),);
+        let actual = check_winner(board, &Rules::classic());
+        assert_eq!(actual, BoardState::Winner(Player::Player1, vec![1, 4, 7]),);
     }
     #[test]
     fn right_col_human_win() {
@@ -265,8 +525,8 @@ mod test {
             Field::Empty,
             Field::X,
         ];
-        let actual = check_winner(board);
-        assert_eq!(actual, BoardState::Winner(Player::Player1, (2, 5, 8)),);
+        let actual = check_winner(board, &Rules::classic());
+        assert_eq!(actual, BoardState::Winner(Player::Player1, vec![2, 5, 8]),);
     }
     #[test]
     fn left_col_comp_win() {
@@ -281,8 +541,8 @@ mod test {
             Field::Empty,
             Field::Empty,
         ];
-        let actual = check_winner(board);
-        assert_eq!(actual, BoardState::Winner(Player::Player2, (0, 3, 6)),);
+        let actual = check_winner(board, &Rules::classic());
+        assert_eq!(actual, BoardState::Winner(Player::Player2, vec![0, 3, 6]),);
     }
     #[test]
     fn mid_col_comp_win() {
@@ -297,8 +557,8 @@ mod test {
             Field::O,
             Field::Empty,
         ];
-        let actual = check_winner(board);
-        assert_eq!(actual, BoardState::Winner(Player::Player2, (1, 4, 7)),);
+        let actual = check_winner(board, &Rules::classic());
+        assert_eq!(actual, BoardState::Winner(Player::Player2, vec![1, 4, 7]),);
     }
     #[test]
     fn right_col_comp_win() {
@@ -313,8 +573,8 @@ mod test {
             Field::Empty,
             Field::O,
         ];
-        let actual = check_winner(board);
-        assert_eq!(actual, BoardState::Winner(Player::Player2, (2, 5, 8)),);
+        let actual = check_winner(board, &Rules::classic());
+        assert_eq!(actual, BoardState::Winner(Player::Player2, vec![2, 5, 8]),);
     }
     #[test]
     fn left_diag_human_win() {
@@ -329,8 +589,8 @@ mod test {
             Field::Empty,
             Field::X,
         ];
-        let actual = check_winner(board);
-        assert_eq!(actual, BoardState::Winner(Player::Player1, (0, 4, 8)),);
+        let actual = check_winner(board, &Rules::classic());
+        assert_eq!(actual, BoardState::Winner(Player::Player1, vec![0, 4, 8]),);
     }
     #[test]
     fn right_diag_human_win() {
@@ -345,8 +605,8 @@ mod test {
             Field::Empty,
             Field::Empty,
         ];
-        let actual = check_winner(board);
-        assert_eq!(actual, BoardState::Winner(Player::Player1, (2, 4, 6)),);
+        let actual = check_winner(board, &Rules::classic());
+        assert_eq!(actual, BoardState::Winner(Player::Player1, vec![2, 4, 6]),);
     }
     #[test]
     fn left_diag_comp_win() {
@@ -361,8 +621,8 @@ mod test {
             Field::Empty,
             Field::O,
         ];
-        let actual = check_winner(board);
-        assert_eq!(actual, BoardState::Winner(Player::Player2, (0, 4, 8)),);
+        let actual = check_winner(board, &Rules::classic());
+        assert_eq!(actual, BoardState::Winner(Player::Player2, vec![0, 4, 8]),);
     }
     #[test]
     fn right_diag_comp_win() {
@@ -377,26 +637,33 @@ mod test {
             Field::Empty,
             Field::Empty,
         ];
-        let actual = check_winner(board);
-        assert_eq!(actual, BoardState::Winner(Player::Player2, (2, 4, 6)),);
+        let actual = check_winner(board, &Rules::classic());
+        assert_eq!(actual, BoardState::Winner(Player::Player2, vec![2, 4, 6]),);
     }
     #[test]
     fn computer_optimal_play() {
-        let mut field = Field::X;
-        let mut game = Board::new(Rect::from_w_h(800.0, 800.0));
+        let mut game = Board::new(
+            Rect::from_w_h(800.0, 800.0),
+            PlayerMode::PlayUndecided,
+            Rules::classic(),
+        );
         let mut winner = None;
         (0..90).any(|_| {
-            let eval = minimax(game.state, &game.board, game.current_player, 0);
-            game.board[eval.position] = field;
-            game.made_move();
+            let eval = minimax(
+                &game.state,
+                &game.board,
+                &game.rules,
+                game.current_player,
+                0,
+            );
+            game.play(eval.play);
             match game.state {
                 BoardState::InGame => {
-                    field = -field;
                     return false;
                 }
                 BoardState::Tie => {
-                    // game.board = (0..9).map(|_| Field::Empty).collect();
-                    let new_game = Board::new(game.rect);
+                    let new_game =
+                        Board::new(game.rect, PlayerMode::PlayUndecided, game.rules);
                     std::mem::replace(&mut game, new_game);
                     return false;
                 }
@@ -408,4 +675,408 @@ mod test {
         });
         assert!(winner.is_none());
     }
+    #[test]
+    fn connect_four_diag_win() {
+        let rules = Rules::connect_four();
+        let mut board = vec![Field::Empty; rules.size()];
+        for &i in &[38, 32, 26, 20] {
+            board[i] = Field::O;
+        }
+        let actual = check_winner(&board, &rules);
+        assert_eq!(
+            actual,
+            BoardState::Winner(Player::Player2, vec![20, 26, 32, 38]),
+        );
+    }
+    #[test]
+    fn connect_four_three_is_not_a_win() {
+        let rules = Rules::connect_four();
+        let mut board = vec![Field::Empty; rules.size()];
+        for &i in &[35, 36, 37] {
+            board[i] = Field::X;
+        }
+        assert_eq!(check_winner(&board, &rules), BoardState::InGame);
+    }
+    #[test]
+    fn gravity_moves_are_drops() {
+        let rules = Rules::connect_four();
+        let mut board = vec![Field::Empty; rules.size()];
+        board[38] = Field::X;
+        let mut moves: Vec<usize> = rules
+            .legal_moves(&board, Player::Player1)
+            .iter()
+            .map(|play| play.target())
+            .collect();
+        moves.sort();
+        assert_eq!(moves, vec![31, 35, 36, 37, 39, 40, 41]);
+        assert_eq!(rules.target(&board, 3), Some(31));
+    }
+    #[test]
+    fn gravity_full_column_is_not_offered() {
+        let rules = Rules::gravity();
+        let mut board = vec![Field::Empty; rules.size()];
+        for &i in &[0, 3, 6] {
+            board[i] = Field::X;
+        }
+        assert_eq!(rules.target(&board, 3), None);
+        assert!(rules
+            .legal_moves(&board, Player::Player2)
+            .iter()
+            .all(|play| rules.column(play.target()) != 0));
+    }
+    #[test]
+    fn connect_four_blocks_open_three() {
+        let rules = Rules::connect_four();
+        let mut board = vec![Field::Empty; rules.size()];
+        for &i in &[35, 36, 37] {
+            board[i] = Field::X;
+        }
+        board[41] = Field::O;
+        board[40] = Field::O;
+        let eval = minimax(&BoardState::InGame, &board, &rules, Player::Player2, 0);
+        assert_eq!(eval.play, Move::Place(38));
+    }
+    #[test]
+    fn morris_slides_only_to_adjacent_cells() {
+        let rules = Rules::three_mens_morris();
+        let board = &[
+            Field::X,
+            Field::O,
+            Field::Empty,
+            Field::O,
+            Field::X,
+            Field::Empty,
+            Field::Empty,
+            Field::X,
+            Field::O,
+        ];
+        let mut moves = rules.legal_moves(board, Player::Player1);
+        moves.sort_by_key(|play| match play {
+            Move::Slide(from, to) => (*from, *to),
+            Move::Place(to) => (0, *to),
+        });
+        assert_eq!(
+            moves,
+            vec![
+                Move::Slide(4, 2),
+                Move::Slide(4, 5),
+                Move::Slide(4, 6),
+                Move::Slide(7, 6),
+            ]
+        );
+    }
+    #[test]
+    fn morris_edge_cells_are_not_diagonally_adjacent() {
+        let rules = Rules::three_mens_morris();
+        assert!(rules.adjacent(0, 4));
+        assert!(rules.adjacent(2, 4));
+        assert!(!rules.adjacent(1, 3));
+        assert!(!rules.adjacent(5, 7));
+    }
+    #[test]
+    fn morris_places_until_three_pieces() {
+        let rules = Rules::three_mens_morris();
+        let mut game = Board::new(
+            Rect::from_w_h(800.0, 800.0),
+            PlayerMode::PlayUndecided,
+            rules,
+        );
+        for &cell in &[0, 1, 5, 3, 7] {
+            game.play(Move::Place(cell));
+        }
+        assert!(!rules.is_sliding(&game.board, Player::Player2));
+        game.play(Move::Place(8));
+        assert!(rules.is_sliding(&game.board, Player::Player1));
+        assert!(rules
+            .legal_moves(&game.board, Player::Player1)
+            .iter()
+            .all(|play| match play {
+                Move::Slide(..) => true,
+                Move::Place(_) => false,
+            }));
+    }
+    #[test]
+    fn morris_repetition_is_a_draw() {
+        let mut game = Board::new(
+            Rect::from_w_h(800.0, 800.0),
+            PlayerMode::PlayUndecided,
+            Rules::three_mens_morris(),
+        );
+        for &cell in &[0, 1, 5, 3, 7, 8] {
+            game.play(Move::Place(cell));
+        }
+        for _ in 0..2 {
+            game.play(Move::Slide(0, 4));
+            game.play(Move::Slide(1, 2));
+            game.play(Move::Slide(4, 0));
+            game.play(Move::Slide(2, 1));
+        }
+        assert_eq!(game.state, BoardState::Tie);
+    }
+    #[test]
+    fn morris_search_terminates() {
+        let rules = Rules::three_mens_morris();
+        let board = &[
+            Field::X,
+            Field::O,
+            Field::Empty,
+            Field::O,
+            Field::Empty,
+            Field::X,
+            Field::Empty,
+            Field::X,
+            Field::O,
+        ];
+        let eval = minimax(&BoardState::InGame, board, &rules, Player::Player1, 0);
+        assert!(rules
+            .legal_moves(board, Player::Player1)
+            .contains(&eval.play));
+    }
+    #[test]
+    fn infinite_fourth_mark_removes_oldest() {
+        let mut game = Board::new(
+            Rect::from_w_h(800.0, 800.0),
+            PlayerMode::PlayUndecided,
+            Rules::infinite(),
+        );
+        for &cell in &[0, 1, 3, 2, 5, 7] {
+            game.play(Move::Place(cell));
+        }
+        assert_eq!(game.rules.vanishing(&game.queues[0]), Some(0));
+        game.play(Move::Place(8));
+        assert_eq!(game.board[0], Field::Empty);
+        assert_eq!(game.board[8], Field::X);
+        assert_eq!(game.queues[0], vec![3, 5, 8]);
+        assert_eq!(game.state, BoardState::InGame);
+    }
+    #[test]
+    fn infinite_vanished_mark_does_not_count() {
+        let mut game = Board::new(
+            Rect::from_w_h(800.0, 800.0),
+            PlayerMode::PlayUndecided,
+            Rules::infinite(),
+        );
+        // X's first mark at 0 vanishes as the fourth lands at 2
+        for &cell in &[0, 4, 1, 8, 6, 5, 2] {
+            game.play(Move::Place(cell));
+        }
+        assert_eq!(game.board[0], Field::Empty);
+        assert_eq!(game.state, BoardState::InGame);
+    }
+    #[test]
+    fn infinite_search_sees_vanishing_marks() {
+        let mut game = Board::new(
+            Rect::from_w_h(800.0, 800.0),
+            PlayerMode::PlayUndecided,
+            Rules::infinite(),
+        );
+        for &cell in &[4, 8, 0, 6, 1, 5] {
+            game.play(Move::Place(cell));
+        }
+        // the centre mark goes, so only the top row is left to complete
+        let eval = best_move(&game);
+        assert_eq!(eval.play, Move::Place(2));
+    }
+    #[test]
+    fn make_and_unmake_restore_queues() {
+        let rules = Rules::infinite();
+        let mut board = vec![Field::Empty; rules.size()];
+        let mut queue = VecDeque::new();
+        for &cell in &[0, 1, 2] {
+            rules.make_move(&mut board, &mut queue, Move::Place(cell), Field::O);
+        }
+        let before = (board.clone(), queue.clone());
+        let vanished = rules.make_move(&mut board, &mut queue, Move::Place(5), Field::O);
+        assert_eq!(vanished, Some(0));
+        rules.unmake_move(&mut board, &mut queue, Move::Place(5), Field::O, vanished);
+        assert_eq!((board, queue), before);
+    }
+    #[test]
+    fn blocked_cells_never_win() {
+        let board = &[
+            Field::Blocked,
+            Field::Blocked,
+            Field::Blocked,
+            Field::X,
+            Field::Blocked,
+            Field::X,
+            Field::Empty,
+            Field::Empty,
+            Field::Empty,
+        ];
+        assert_eq!(check_winner(board, &Rules::classic()), BoardState::InGame);
+    }
+    #[test]
+    fn blocked_cell_stops_a_drop() {
+        let rules = Rules::gravity();
+        let mut board = vec![Field::Empty; rules.size()];
+        board[3] = Field::Blocked;
+        assert_eq!(rules.target(&board, 6), Some(0));
+        assert_eq!(rules.target(&board, 4), Some(7));
+    }
+    #[test]
+    fn random_setup_is_repeatable() {
+        let rules = Rules::connect_four();
+        let mut first = vec![Field::Empty; rules.size()];
+        let mut second = vec![Field::Empty; rules.size()];
+        Setup::Random(42).apply(&rules, &mut first);
+        Setup::Random(42).apply(&rules, &mut second);
+        assert_eq!(first, second);
+        assert_eq!(first.iter().filter(|&&v| v == Field::Blocked).count(), 4);
+        assert_eq!(first.iter().filter(|&&v| v == Field::X).count(), 2);
+        assert_eq!(first.iter().filter(|&&v| v == Field::O).count(), 2);
+    }
+    #[test]
+    fn random_marks_fall_under_gravity() {
+        let rules = Rules::connect_four();
+        for seed in 0..100 {
+            let mut cells = vec![Field::Empty; rules.size()];
+            Setup::Random(seed).apply(&rules, &mut cells);
+            for cell in (0..rules.size())
+                .filter(|&cell| cells[cell] == Field::X || cells[cell] == Field::O)
+            {
+                let (column, row) = (rules.column(cell), rules.row(cell));
+                assert!(
+                    row + 1 == rules.height
+                        || cells[rules.cell(column, row + 1)] != Field::Empty,
+                    "seed {} left a mark floating at {}",
+                    seed,
+                    cell
+                );
+            }
+        }
+    }
+    #[test]
+    fn random_setup_never_starts_with_a_line() {
+        let rules = Rules::classic();
+        for seed in 0..200 {
+            let mut cells = vec![Field::Empty; rules.size()];
+            Setup::Random(seed).apply(&rules, &mut cells);
+            assert_eq!(cells.iter().filter(|&&v| v == Field::X).count(), 1);
+            assert_eq!(cells.iter().filter(|&&v| v == Field::O).count(), 1);
+            assert_eq!(check_winner(&cells, &rules), BoardState::InGame);
+        }
+    }
+    #[test]
+    fn preset_with_extra_mark_lets_o_start() {
+        let game = Board::new(
+            Rect::from_w_h(800.0, 800.0),
+            PlayerMode::PlayUndecided,
+            Rules::classic(),
+        )
+        .with_setup(Setup::Preset(2));
+        assert_eq!(game.board[4], Field::X);
+        assert_eq!(game.current_player, Player::Player2);
+    }
+    #[test]
+    fn preset_for_other_board_is_skipped() {
+        let game = Board::new(
+            Rect::from_w_h(800.0, 800.0),
+            PlayerMode::PlayUndecided,
+            Rules::classic(),
+        )
+        .with_setup(Setup::Preset(4));
+        assert!(game.board.iter().all(|&v| v == Field::Empty));
+    }
+    #[test]
+    fn presets_are_checked_against_the_board() {
+        assert!(Setup::Preset(0).check(&Rules::classic()).is_ok());
+        assert_eq!(
+            Setup::Preset(4).check(&Rules::classic()),
+            Err("Pillars is for a 7 by 6 board".to_string())
+        );
+        assert!(Setup::Preset(99).check(&Rules::classic()).is_err());
+    }
+    #[test]
+    fn cycling_setups_leaves_out_other_boards() {
+        let rules = Rules::connect_four();
+        assert_eq!(Setup::Empty.next(7, &rules), Setup::Preset(4));
+        assert_eq!(Setup::Preset(4).next(7, &rules), Setup::Random(7));
+        assert_eq!(
+            Setup::Preset(3).next(7, &Rules::classic()),
+            Setup::Random(7)
+        );
+    }
+    #[test]
+    fn torus_row_wraps_around() {
+        let rules = Rules::torus();
+        let mut board = vec![Field::Empty; rules.size()];
+        for &i in &[8, 9, 5, 6] {
+            board[i] = Field::X;
+        }
+        let actual = check_winner(&board, &rules);
+        assert_eq!(
+            actual,
+            BoardState::Winner(Player::Player1, vec![8, 9, 5, 6]),
+        );
+    }
+    #[test]
+    fn torus_lines_are_not_repeated() {
+        let rules = Rules {
+            torus: true,
+            ..Rules::classic()
+        };
+        assert_eq!(rules.winning_lines().len(), 12);
+        assert_eq!(Rules::classic().winning_lines().len(), 8);
+        assert_eq!(Rules::connect_four().winning_lines().len(), 69);
+    }
+    #[test]
+    fn torus_steps_across_the_edge() {
+        let rules = Rules::torus();
+        assert_eq!(rules.step(9, 5), (1, 0));
+        assert_eq!(rules.step(20, 1), (1, 1));
+        assert_eq!(rules.step(4, 8), (-1, 1));
+    }
+    #[test]
+    fn torus_search_blocks_wrapped_three() {
+        let rules = Rules::torus();
+        let mut board = vec![Field::Empty; rules.size()];
+        for &i in &[8, 9, 5] {
+            board[i] = Field::X;
+        }
+        board[12] = Field::O;
+        board[18] = Field::O;
+        let eval = minimax(&BoardState::InGame, &board, &rules, Player::Player2, 0);
+        assert!(eval.play == Move::Place(6) || eval.play == Move::Place(7));
+    }
+    #[test]
+    fn hex_has_three_directions() {
+        assert_eq!(Rules::hex().winning_lines().len(), 24);
+    }
+    #[test]
+    fn hex_wins_along_the_third_axis() {
+        let rules = Rules::hex();
+        let mut board = vec![Field::Empty; rules.size()];
+        for &i in &[3, 7, 11, 15] {
+            board[i] = Field::O;
+        }
+        assert_eq!(
+            check_winner(&board, &rules),
+            BoardState::Winner(Player::Player2, vec![3, 7, 11, 15])
+        );
+    }
+    #[test]
+    fn hex_square_diagonal_is_no_line() {
+        let rules = Rules::hex();
+        let mut board = vec![Field::Empty; rules.size()];
+        for &i in &[0, 6, 12, 18] {
+            board[i] = Field::X;
+        }
+        assert_eq!(check_winner(&board, &rules), BoardState::InGame);
+    }
+    #[test]
+    fn ranking_agrees_with_minimax() {
+        let rules = Rules::classic();
+        let mut board = vec![Field::Empty; rules.size()];
+        board[0] = Field::X;
+        board[1] = Field::X;
+        board[4] = Field::O;
+        let ranking = rank_moves(&board, &rules, Player::Player2);
+        assert_eq!(ranking.len(), 6);
+        assert_eq!(ranking[0].play, Move::Place(2));
+        let best = minimax(&BoardState::InGame, &board, &rules, Player::Player2, 0);
+        assert_eq!(ranking[0].score, best.score);
+        // everything else lets X complete the top row
+        assert!(ranking[1..].iter().all(|eval| eval.score < -WIN / 2));
+    }
 }
@@ -0,0 +1,242 @@
+//! Chess-style clocks for games played in one window. Network and server
+//! games are played without them, since the two sides' clocks would drift
+//! apart.
+use crate::board::{Board, BoardState};
+use crate::player::Player;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum TimeControl {
+    Unlimited,
+    /// A fixed amount for the whole game.
+    SuddenDeath(Duration),
+    /// An amount for the game, with `increment` added after each move.
+    Fischer {
+        total: Duration,
+        increment: Duration,
+    },
+    /// The same amount for every move, with nothing carried over.
+    PerMove(Duration),
+}
+
+/// The time controls the menu cycles through.
+pub const TIME_CONTROLS: [TimeControl; 5] = [
+    TimeControl::Unlimited,
+    TimeControl::SuddenDeath(Duration::from_secs(60)),
+    TimeControl::SuddenDeath(Duration::from_secs(180)),
+    TimeControl::Fischer {
+        total: Duration::from_secs(180),
+        increment: Duration::from_secs(2),
+    },
+    TimeControl::PerMove(Duration::from_secs(10)),
+];
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        TimeControl::Unlimited
+    }
+}
+
+impl TimeControl {
+    /// The time control shown after this one when cycling through the menu.
+    pub fn next(self) -> TimeControl {
+        let index = TIME_CONTROLS.iter().position(|&c| c == self).unwrap_or(0);
+        TIME_CONTROLS[(index + 1) % TIME_CONTROLS.len()]
+    }
+
+    /// Each side's time before the first move, if the game is timed at all.
+    fn initial(self) -> Option<Duration> {
+        match self {
+            TimeControl::Unlimited => None,
+            TimeControl::SuddenDeath(total) => Some(total),
+            TimeControl::Fischer { total, .. } => Some(total),
+            TimeControl::PerMove(time) => Some(time),
+        }
+    }
+}
+
+impl std::string::ToString for TimeControl {
+    fn to_string(&self) -> String {
+        match self {
+            TimeControl::Unlimited => "No clock".to_string(),
+            TimeControl::SuddenDeath(total) => show(*total),
+            TimeControl::Fischer { total, increment } => {
+                format!("{} + {}s", show(*total), increment.as_secs())
+            }
+            TimeControl::PerMove(time) => format!("{}s a move", time.as_secs()),
+        }
+    }
+}
+
+/// Minutes and seconds, rounded up so a clock reads 0:00 only once it has run
+/// out.
+pub fn show(time: Duration) -> String {
+    let seconds = (time.as_millis() + 999) / 1000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[derive(Debug, Clone)]
+pub struct Clock {
+    pub control: TimeControl,
+    /// Time left for each side, by `Player::index`.
+    pub remaining: [Duration; 2],
+    /// The side that ran out of time, if any.
+    pub flagged: Option<Player>,
+    /// Moves seen so far, to notice new ones.
+    moves: usize,
+}
+
+impl Clock {
+    /// A clock for a game with no moves yet, or `None` for untimed games.
+    pub fn new(control: TimeControl) -> Option<Clock> {
+        let time = control.initial()?;
+        Some(Clock {
+            control,
+            remaining: [time, time],
+            flagged: None,
+            moves: 0,
+        })
+    }
+
+    /// Credits `mover` for the move just made.
+    pub fn moved(&mut self, mover: Player) {
+        let remaining = &mut self.remaining[mover.index()];
+        match self.control {
+            TimeControl::Fischer { increment, .. } => *remaining += increment,
+            TimeControl::PerMove(time) => *remaining = time,
+            _ => {}
+        }
+    }
+
+    /// Runs the clock of the side that was to move at the last tick for
+    /// `elapsed`, then credits each move made on `board` since.
+    pub fn tick(&mut self, board: &mut Board, elapsed: Duration) {
+        let first = board.first_player();
+        let mover = |index: usize| match index % 2 {
+            0 => first,
+            _ => -first,
+        };
+        let side = if board.moves.len() > self.moves {
+            mover(self.moves)
+        } else {
+            board.current_player
+        };
+        self.spend(board, side, elapsed);
+        while self.moves < board.moves.len() {
+            self.moved(mover(self.moves));
+            self.moves += 1;
+        }
+    }
+
+    /// Takes `elapsed` off `side`'s time, and ends the game in the other
+    /// side's favour if it runs out.
+    pub fn spend(&mut self, board: &mut Board, side: Player, elapsed: Duration) {
+        if board.state != BoardState::InGame {
+            return;
+        }
+        let remaining = &mut self.remaining[side.index()];
+        *remaining = remaining.checked_sub(elapsed).unwrap_or_default();
+        if *remaining == Duration::from_secs(0) {
+            self.flagged = Some(side);
+            board.state = BoardState::Winner(-side, vec![]);
+        }
+    }
+
+    /// Whether this clock was started for `board`, rather than a game that
+    /// has since been replaced.
+    pub fn follows(&self, board: &Board) -> bool {
+        // a board still being played after the flag fell is a new game, even
+        // when the old one was lost before its first move
+        let replaced = self.flagged.is_some() && board.state == BoardState::InGame;
+        board.moves.len() >= self.moves && !replaced
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::board::test::board;
+    #[allow(unused_imports)]
+    use crate::rules::{Move, Rules};
+
+    #[test]
+    fn fischer_adds_the_increment_after_a_move() {
+        let control = TimeControl::Fischer {
+            total: Duration::from_secs(60),
+            increment: Duration::from_secs(2),
+        };
+        let mut clock = Clock::new(control).unwrap();
+        let mut board = board(Rules::classic());
+        clock.tick(&mut board, Duration::from_secs(5));
+        board.play(Move::Place(4));
+        clock.tick(&mut board, Duration::from_millis(0));
+        clock.tick(&mut board, Duration::from_secs(1));
+        assert_eq!(clock.remaining[0], Duration::from_secs(57));
+        assert_eq!(clock.remaining[1], Duration::from_secs(59));
+    }
+    #[test]
+    fn per_move_time_does_not_carry_over() {
+        let mut clock =
+            Clock::new(TimeControl::PerMove(Duration::from_secs(10))).unwrap();
+        let mut board = board(Rules::classic());
+        clock.tick(&mut board, Duration::from_secs(3));
+        board.play(Move::Place(0));
+        clock.tick(&mut board, Duration::from_millis(0));
+        assert_eq!(clock.remaining[0], Duration::from_secs(10));
+        assert_eq!(show(Duration::from_millis(9_001)), "0:10");
+    }
+    #[test]
+    fn running_out_loses() {
+        let control = TimeControl::SuddenDeath(Duration::from_secs(60));
+        let mut clock = Clock::new(control).unwrap();
+        let mut board = board(Rules::classic());
+        board.play(Move::Place(0));
+        clock.tick(&mut board, Duration::from_millis(0));
+        clock.tick(&mut board, Duration::from_secs(61));
+        assert_eq!(clock.flagged, Some(Player::Player2));
+        assert_eq!(board.state, BoardState::Winner(Player::Player1, vec![]));
+        assert_eq!(clock.remaining[0], Duration::from_secs(60));
+        assert!(Clock::new(TimeControl::Unlimited).is_none());
+    }
+    #[test]
+    fn a_game_lost_before_the_first_move_is_not_followed() {
+        let mut clock =
+            Clock::new(TimeControl::PerMove(Duration::from_secs(10))).unwrap();
+        let mut lost = board(Rules::classic());
+        clock.tick(&mut lost, Duration::from_secs(11));
+        assert_eq!(clock.flagged, Some(Player::Player1));
+        assert!(clock.follows(&lost));
+        // the rematch has no moves either, but needs a clock of its own
+        assert!(!clock.follows(&board(Rules::classic())));
+    }
+    #[test]
+    fn a_move_and_its_reply_in_one_frame_are_both_credited() {
+        let control = TimeControl::Fischer {
+            total: Duration::from_secs(60),
+            increment: Duration::from_secs(2),
+        };
+        let mut clock = Clock::new(control).unwrap();
+        let mut game = board(Rules::classic());
+        game.play(Move::Place(4));
+        game.play(Move::Place(0));
+        // the frame's time went on X's move, before O answered
+        clock.tick(&mut game, Duration::from_secs(5));
+        assert_eq!(clock.remaining[0], Duration::from_secs(57));
+        assert_eq!(clock.remaining[1], Duration::from_secs(62));
+        assert!(clock.follows(&game));
+
+        let mut clock =
+            Clock::new(TimeControl::PerMove(Duration::from_secs(10))).unwrap();
+        let mut board = board(Rules::classic());
+        clock.tick(&mut board, Duration::from_secs(4));
+        board.play(Move::Place(4));
+        clock.tick(&mut board, Duration::from_secs(1));
+        // O thinks for three seconds before answering
+        clock.spend(&mut board, Player::Player2, Duration::from_secs(3));
+        assert_eq!(clock.remaining[1], Duration::from_secs(7));
+        board.play(Move::Place(0));
+        clock.tick(&mut board, Duration::from_millis(0));
+        assert_eq!(clock.remaining, [Duration::from_secs(10); 2]);
+    }
+}
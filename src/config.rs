@@ -0,0 +1,499 @@
+//! Settings read from a TOML file at startup. Every setting is optional; a
+//! file with all of them at their defaults reads:
+//!
+//! ```toml
+//! [window]
+//! width = 600
+//! height = 600
+//!
+//! [theme]
+//! name = "light"  # or "dark", "paper", "high-contrast", or one of your own
+//! directory = "themes"  # where your own themes are, see `theme`
+//!
+//! [colors]  # each replaces the theme's own
+//! # background = "#87ceeb"
+//! # button = "#a9a9a9"
+//! # text = "#000000"
+//!
+//! [fonts]
+//! title = 24
+//! button = 20
+//! status = 16
+//!
+//! [game]
+//! mode = "menu"  # or "single-player", "hot-seat", "lobby"
+//! variant = "classic"
+//! difficulty = "hard"
+//! best_of = 1  # games in a match against the computer or in the hot seat
+//!
+//! [keys]
+//! confirm = "Return"
+//! erase = "Back"
+//! menu = "Escape"
+//! up = "Up"
+//! down = "Down"
+//! left = "Left"
+//! right = "Right"
+//! numpad = false  # number keys laid out like a numeric keypad, 7 at the top
+//!
+//! [animation]
+//! enabled = true
+//! mark = 250  # milliseconds to draw a new mark
+//! line = 400  # for the winning line to sweep across
+//! fade = 300  # for a new board to fade in
+//!
+//! [accessibility]
+//! high_contrast = false  # the high-contrast theme, whatever is picked above
+//! # announce = "stdout"  # or a file to add game events to as text
+//! ```
+//!
+//! Keys go by their names on the keyboard, such as `A`, `Key1`, `Numpad1`,
+//! `Escape` or `Up`.
+use crate::brain::Difficulty;
+use crate::rules::Variant;
+use crate::theme;
+use nannou::prelude::*;
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_PATH: &str = "config.toml";
+
+/// Names the file to read instead of `DEFAULT_PATH`.
+pub const PATH_VARIABLE: &str = "TIC_TAC_TOE_CONFIG";
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window: Window,
+    pub theme: ThemeChoice,
+    pub colors: Colors,
+    pub fonts: Fonts,
+    pub game: Game,
+    pub keys: Keys,
+    pub animation: Animation,
+    pub accessibility: Accessibility,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Window {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Window {
+            width: 600,
+            height: 600,
+        }
+    }
+}
+
+/// A color written as `#rrggbb`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Color(pub Srgb<u8>);
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Color, String> {
+        let invalid = || format!("expected a color like \"#87ceeb\", found {:?}", text);
+        if !text.starts_with('#') || text.len() != 7 {
+            return Err(invalid());
+        }
+        let channel = |i: usize| {
+            let digits = text.get(i..i + 2).ok_or_else(invalid)?;
+            u8::from_str_radix(digits, 16).map_err(|_| invalid())
+        };
+        Ok(Color(srgb(channel(1)?, channel(3)?, channel(5)?)))
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Color(color) = self;
+        write!(f, "#{:02x}{:02x}{:02x}", color.red, color.green, color.blue)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeChoice {
+    pub name: String,
+    pub directory: PathBuf,
+}
+
+impl Default for ThemeChoice {
+    fn default() -> Self {
+        ThemeChoice {
+            name: "light".to_string(),
+            directory: PathBuf::from(theme::DEFAULT_DIRECTORY),
+        }
+    }
+}
+
+/// Colors that take the place of the theme's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Colors {
+    pub background: Option<Color>,
+    pub button: Option<Color>,
+    pub text: Option<Color>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Fonts {
+    pub title: u32,
+    pub button: u32,
+    pub status: u32,
+}
+
+impl Default for Fonts {
+    fn default() -> Self {
+        Fonts {
+            title: 24,
+            button: 20,
+            status: 16,
+        }
+    }
+}
+
+/// The screen shown once a profile is picked.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StartMode {
+    Menu,
+    SinglePlayer,
+    HotSeat,
+    Lobby,
+}
+
+impl Default for StartMode {
+    fn default() -> Self {
+        StartMode::Menu
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Game {
+    pub mode: StartMode,
+    pub variant: Variant,
+    pub difficulty: Difficulty,
+    /// How many games a match is the best of, see `session::Series`.
+    pub best_of: u32,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Game {
+            mode: StartMode::default(),
+            variant: Variant::default(),
+            difficulty: Difficulty::default(),
+            best_of: 1,
+        }
+    }
+}
+
+/// A key named as in `Key`, such as `Return` or `Numpad5`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Binding(pub Key);
+
+#[rustfmt::skip]
+const BINDABLE: [Key; 77] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I,
+    Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R,
+    Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6,
+    Key::Key7, Key::Key8, Key::Key9,
+    Key::Numpad0, Key::Numpad1, Key::Numpad2, Key::Numpad3, Key::Numpad4,
+    Key::Numpad5, Key::Numpad6, Key::Numpad7, Key::Numpad8, Key::Numpad9,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8,
+    Key::F9, Key::F10, Key::F11, Key::F12,
+    Key::Up, Key::Down, Key::Left, Key::Right,
+    Key::Return, Key::NumpadEnter, Key::Escape, Key::Back, Key::Space, Key::Tab,
+    Key::Minus, Key::Equals, Key::Add, Key::Subtract,
+    Key::Home, Key::End, Key::PageUp, Key::PageDown, Key::Delete,
+];
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Binding, String> {
+        BINDABLE
+            .iter()
+            .find(|key| format!("{:?}", key) == name)
+            .map(|&key| Binding(key))
+            .ok_or_else(|| format!("there is no key called {:?}", name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keys {
+    /// Finishes typing, as in naming a new profile.
+    pub confirm: Binding,
+    /// Takes back the last letter typed.
+    pub erase: Binding,
+    /// Goes back to the main menu.
+    pub menu: Binding,
+    /// Move the cursor on the board, or the focus between buttons.
+    pub up: Binding,
+    pub down: Binding,
+    pub left: Binding,
+    pub right: Binding,
+    /// Whether the number keys put 7 8 9 on the top row, as on a numeric
+    /// keypad, rather than 1 2 3 as on a phone.
+    pub numpad: bool,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys {
+            confirm: Binding(Key::Return),
+            erase: Binding(Key::Back),
+            menu: Binding(Key::Escape),
+            up: Binding(Key::Up),
+            down: Binding(Key::Down),
+            left: Binding(Key::Left),
+            right: Binding(Key::Right),
+            numpad: false,
+        }
+    }
+}
+
+impl Keys {
+    /// Every binding with its setting's name.
+    fn all(&self) -> Vec<(&'static str, Key)> {
+        vec![
+            ("confirm", self.confirm.0),
+            ("erase", self.erase.0),
+            ("menu", self.menu.0),
+            ("up", self.up.0),
+            ("down", self.down.0),
+            ("left", self.left.0),
+            ("right", self.right.0),
+        ]
+    }
+}
+
+/// How long each animation takes, in milliseconds, see `animation`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Animation {
+    pub enabled: bool,
+    pub mark: u64,
+    pub line: u64,
+    pub fade: u64,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation {
+            enabled: true,
+            mark: 250,
+            line: 400,
+            fade: 300,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Accessibility {
+    /// Uses the `theme::HIGH_CONTRAST` theme, whichever one is picked.
+    pub high_contrast: bool,
+    /// Where to write game events as text: `announce::STDOUT`, or a file.
+    pub announce: Option<String>,
+}
+
+/// Why the settings couldn't be used.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// The file named by `PATH_VARIABLE`, or else `DEFAULT_PATH`.
+    pub fn path() -> PathBuf {
+        std::env::var_os(PATH_VARIABLE)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_PATH))
+    }
+
+    /// Reads and checks the settings at `path`. A missing file means every
+    /// setting keeps its default.
+    pub fn load(path: impl AsRef<Path>) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let fail = |message: String| ConfigError {
+            path: path.to_path_buf(),
+            message,
+        };
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(ref error) if error.kind() == io::ErrorKind::NotFound => {
+                return Ok(Config::default());
+            }
+            Err(error) => return Err(fail(error.to_string())),
+        };
+        Config::parse(&text).map_err(fail)
+    }
+
+    /// Reads settings from TOML text and checks them.
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(text).map_err(|error| error.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// The theme to start with.
+    pub fn theme_name(&self) -> &str {
+        if self.accessibility.high_contrast {
+            theme::HIGH_CONTRAST
+        } else {
+            &self.theme.name
+        }
+    }
+
+    /// Turns down settings that parse but can't work.
+    pub fn validate(&self) -> Result<(), String> {
+        for &(name, value) in
+            &[("width", self.window.width), ("height", self.window.height)]
+        {
+            if !(300..=4000).contains(&value) {
+                return Err(format!(
+                    "window.{} must be between 300 and 4000, not {}",
+                    name, value
+                ));
+            }
+        }
+        let fonts = &self.fonts;
+        for &(name, size) in &[
+            ("title", fonts.title),
+            ("button", fonts.button),
+            ("status", fonts.status),
+        ] {
+            if !(8..=72).contains(&size) {
+                return Err(format!(
+                    "fonts.{} must be between 8 and 72, not {}",
+                    name, size
+                ));
+            }
+        }
+        if !(1..=99).contains(&self.game.best_of) {
+            return Err(format!(
+                "game.best_of must be between 1 and 99, not {}",
+                self.game.best_of
+            ));
+        }
+        let animation = &self.animation;
+        for &(name, length) in &[
+            ("mark", animation.mark),
+            ("line", animation.line),
+            ("fade", animation.fade),
+        ] {
+            if length > 5000 {
+                return Err(format!(
+                    "animation.{} must be at most 5000 milliseconds, not {}",
+                    name, length
+                ));
+            }
+        }
+        let keys = self.keys.all();
+        for (i, (name, key)) in keys.iter().enumerate() {
+            if let Some((other, _)) = keys[..i].iter().find(|(_, k)| k == key) {
+                return Err(format!(
+                    "keys.{} and keys.{} are both bound to {:?}",
+                    other, name, key
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[test]
+    fn missing_settings_keep_their_defaults() {
+        let config = Config::parse("[window]\nwidth = 800\n").unwrap();
+        assert_eq!(config.window.width, 800);
+        assert_eq!(config.window.height, 600);
+        assert_eq!(config.colors, Colors::default());
+        assert_eq!(config.theme.name, "light");
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+    #[test]
+    fn reads_every_kind_of_setting() {
+        let text = r##"
+            [colors]
+            background = "#102030"
+            [game]
+            mode = "hot-seat"
+            variant = "connect-four"
+            difficulty = "easy"
+            best_of = 5
+            [keys]
+            confirm = "Space"
+            numpad = true
+        "##;
+        let config = Config::parse(text).unwrap();
+        assert_eq!(
+            config.colors.background,
+            Some(Color(srgb(0x10, 0x20, 0x30)))
+        );
+        assert_eq!(Color(srgb(0x10, 0x20, 0x30)).to_string(), "#102030");
+        assert_eq!(config.game.mode, StartMode::HotSeat);
+        assert_eq!(config.game.variant, Variant::ConnectFour);
+        assert_eq!(config.game.difficulty, Difficulty::Easy);
+        assert_eq!(config.game.best_of, 5);
+        assert_eq!(config.keys.confirm, Binding(Key::Space));
+        assert!(config.keys.numpad);
+        let text = "[accessibility]\nhigh_contrast = true\nannounce = \"stdout\"\n";
+        let config = Config::parse(text).unwrap();
+        assert_eq!(config.theme_name(), theme::HIGH_CONTRAST);
+        assert_eq!(config.accessibility.announce.as_deref(), Some("stdout"));
+    }
+    #[test]
+    fn explains_what_is_wrong() {
+        let error = Config::parse("[colors]\nbutton = \"grey\"\n").unwrap_err();
+        assert!(error.contains("expected a color like"), "{}", error);
+        let error = Config::parse("[window]\nwidht = 800\n").unwrap_err();
+        assert!(error.contains("widht"), "{}", error);
+        let error = Config::parse("[window]\nheight = 20\n").unwrap_err();
+        assert_eq!(error, "window.height must be between 300 and 4000, not 20");
+        let error = Config::parse("[game]\nbest_of = 0\n").unwrap_err();
+        assert_eq!(error, "game.best_of must be between 1 and 99, not 0");
+        let error = Config::parse("[animation]\nfade = 9000\n").unwrap_err();
+        assert_eq!(
+            error,
+            "animation.fade must be at most 5000 milliseconds, not 9000"
+        );
+        let error = Config::parse("[keys]\nconfirm = \"Back\"\n").unwrap_err();
+        assert_eq!(error, "keys.confirm and keys.erase are both bound to Back");
+        let error = Config::parse("[keys]\nerase = \"Shift\"\n").unwrap_err();
+        assert!(
+            error.contains("there is no key called \"Shift\""),
+            "{}",
+            error
+        );
+    }
+}
@@ -1,15 +1,18 @@
+use crate::rules::Move;
+use serde::Serialize;
 use std::cmp::Ordering;
 use std::fmt;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize)]
 pub struct Eval {
-    pub position: usize,
+    #[serde(rename = "move")]
+    pub play: Move,
     pub score: i64,
 }
 
 impl Eval {
-    pub fn new(position: usize, score: i64) -> Eval {
-        Eval { position, score }
+    pub fn new(play: Move, score: i64) -> Eval {
+        Eval { play, score }
     }
 }
 
@@ -27,7 +30,7 @@ impl PartialOrd for Eval {
 
 impl PartialEq for Eval {
     fn eq(&self, other: &Self) -> bool {
-        (self.position, self.score) == (other.position, other.score)
+        (self.play, self.score) == (other.play, other.score)
     }
 }
 
@@ -35,6 +38,6 @@ impl Eq for Eval {}
 
 impl fmt::Display for Eval {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "pos: {},score: {}", self.position, self.score)
+        writeln!(f, "move: {:?},score: {}", self.play, self.score)
     }
 }
@@ -1,10 +1,14 @@
+use serde::{Deserialize, Serialize};
 use std::ops::Neg;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Field {
     Empty = 0,
     O = 1,
     X = 2,
+    /// A cell nobody can play in.
+    Blocked = 3,
 }
 
 impl Neg for Field {
@@ -15,6 +19,7 @@ impl Neg for Field {
             Field::X => Field::O,
             Field::O => Field::X,
             Field::Empty => Field::Empty,
+            Field::Blocked => Field::Blocked,
         }
     }
 }
@@ -22,7 +27,7 @@ impl Neg for Field {
 impl std::string::ToString for Field {
     fn to_string(&self) -> String {
         match self {
-            Field::Empty => "".to_string(),
+            Field::Empty | Field::Blocked => "".to_string(),
             Field::O => "O".to_string(),
             Field::X => "X".to_string(),
         }
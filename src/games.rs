@@ -0,0 +1,130 @@
+//! Games played away from the window, for the servers.
+use crate::board::{Board, BoardState, MoveError};
+use crate::brain::Difficulty;
+use crate::field::Field;
+use crate::model::PlayerMode;
+use crate::player::Player;
+use crate::rules::{Move, Variant};
+use crate::setup::Setup;
+use nannou::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub struct Game {
+    pub variant: Variant,
+    pub board: Board,
+    /// The side the computer plays, if any.
+    pub bot: Option<Player>,
+    pub difficulty: Difficulty,
+}
+
+/// Everything a client needs to draw a game and pick its next move.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct GameState {
+    pub variant: Variant,
+    pub width: usize,
+    pub height: usize,
+    /// Cells row by row, starting at the top left corner.
+    pub cells: Vec<Field>,
+    pub to_move: Player,
+    pub finished: bool,
+    pub winner: Option<Player>,
+    pub winning_line: Vec<usize>,
+    pub legal_moves: Vec<Move>,
+    /// The cells of X's marks and then O's, each side's oldest first, in
+    /// the order they vanish on boards where marks don't last.
+    #[serde(default)]
+    pub marks: Vec<usize>,
+}
+
+impl Game {
+    pub fn new(variant: Variant, setup: Setup, bot: Option<Player>) -> Self {
+        // nothing is drawn, so the board never needs a real window
+        let rect = Rect::from_w_h(0.0, 0.0);
+        Game {
+            variant,
+            board: Board::new(rect, PlayerMode::PlayUndecided, variant.rules())
+                .with_setup(setup),
+            bot,
+            difficulty: Difficulty::default(),
+        }
+    }
+
+    pub fn with_difficulty(mut self, difficulty: Difficulty) -> Self {
+        self.difficulty = difficulty;
+        self
+    }
+
+    /// Plays `play` for `side`, which can't be the computer's side.
+    pub fn play(&mut self, side: Player, play: Move) -> Result<(), MoveError> {
+        if Some(side) == self.bot {
+            return Err(MoveError::OutOfTurn);
+        }
+        self.board.try_play(play, side)
+    }
+
+    /// Lets the computer move if it is its turn, and says whether it did.
+    pub fn bot_move(&mut self) -> bool {
+        if self.board.state != BoardState::InGame
+            || Some(self.board.current_player) != self.bot
+        {
+            return false;
+        }
+        self.board.computer_move(self.difficulty);
+        true
+    }
+
+    pub fn state(&self) -> GameState {
+        let board = &self.board;
+        let (winner, winning_line) = match &board.state {
+            BoardState::Winner(player, line) => (Some(*player), line.clone()),
+            _ => (None, vec![]),
+        };
+        let finished = board.state != BoardState::InGame;
+        GameState {
+            variant: self.variant,
+            width: board.rules.width,
+            height: board.rules.height,
+            cells: board.board.clone(),
+            to_move: board.current_player,
+            finished,
+            winner,
+            winning_line,
+            legal_moves: if finished {
+                vec![]
+            } else {
+                board.rules.legal_moves(&board.board, board.current_player)
+            },
+            marks: board.queues.iter().flatten().copied().collect(),
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[test]
+    fn bot_answers_and_cannot_be_played_for() {
+        let mut game = Game::new(Variant::Classic, Setup::Empty, Some(Player::Player2));
+        assert!(!game.bot_move());
+        game.play(Player::Player1, Move::Place(4)).unwrap();
+        assert_eq!(
+            game.play(Player::Player2, Move::Place(0)),
+            Err(MoveError::OutOfTurn)
+        );
+        assert!(game.bot_move());
+        let state = game.state();
+        assert_eq!(state.to_move, Player::Player1);
+        assert_eq!(state.cells.iter().filter(|&&v| v == Field::O).count(), 1);
+        assert_eq!(state.legal_moves.len(), 7);
+    }
+    #[test]
+    fn state_serializes_to_json() {
+        let game = Game::new(Variant::ConnectFour, Setup::Empty, None);
+        let json = serde_json::to_value(game.state()).unwrap();
+        assert_eq!(json["variant"], "connect-four");
+        assert_eq!(json["to_move"], "x");
+        assert_eq!(json["cells"][0], "empty");
+        assert_eq!(json["legal_moves"][0], serde_json::json!({ "place": 38 }));
+    }
+}
@@ -0,0 +1,88 @@
+//! Geometry for boards of pointy-topped hexagons.
+//!
+//! Cells use axial coordinates: a cell's column counts steps east and its row
+//! counts steps south-east, so the board as a whole is a rhombus.
+use crate::rules::Rules;
+use nannou::prelude::*;
+
+const SQRT_3: f32 = 1.732_050_8;
+
+/// Distance from a hexagon's centre to its corners, so the board fits `rect`.
+pub fn size(rect: &Rect, rules: &Rules) -> f32 {
+    let (width, height) = (rules.width as f32, rules.height as f32);
+    let across = SQRT_3 * (width + (height - 1.0) / 2.0);
+    let down = 1.5 * (height - 1.0) + 2.0;
+    (rect.w() / across).min(rect.h() / down)
+}
+
+/// Width and height of a single hexagon of the given size.
+pub fn cell_size(size: f32) -> (f32, f32) {
+    (SQRT_3 * size, 2.0 * size)
+}
+
+/// How far the centre moves for the given steps east and south-east.
+pub fn offset(size: f32, columns: f32, rows: f32) -> Vector2 {
+    vec2(size * SQRT_3 * (columns + rows / 2.0), -size * 1.5 * rows)
+}
+
+pub fn center(rect: &Rect, rules: &Rules, cell: usize) -> Point2 {
+    let size = size(rect, rules);
+    let step = offset(size, rules.column(cell) as f32, rules.row(cell) as f32);
+    let across = offset(size, (rules.width - 1) as f32, (rules.height - 1) as f32);
+    pt2(
+        rect.x() + step.x - across.x / 2.0,
+        rect.y() + step.y - across.y / 2.0,
+    )
+}
+
+pub fn corners(center: Point2, size: f32) -> Vec<Point2> {
+    (0..6)
+        .map(|i| {
+            let angle = PI / 6.0 + PI / 3.0 * i as f32;
+            pt2(center.x + size * angle.cos(), center.y + size * angle.sin())
+        })
+        .collect()
+}
+
+/// The cell under `point`, if it is on the board.
+pub fn cell_at(rect: &Rect, rules: &Rules, point: Point2) -> Option<usize> {
+    let size = size(rect, rules);
+    let origin = center(rect, rules, 0);
+    let (x, y) = ((point.x - origin.x) / size, (origin.y - point.y) / size);
+    let row = y / 1.5;
+    let column = x / SQRT_3 - row / 2.0;
+    // round in cube coordinates, fixing up whichever axis rounded the most
+    let third = -column - row;
+    let (mut q, mut r, s) = (column.round(), row.round(), third.round());
+    let (dq, dr, ds) = ((q - column).abs(), (r - row).abs(), (s - third).abs());
+    if dq > dr && dq > ds {
+        q = -r - s;
+    } else if dr > ds {
+        r = -q - s;
+    }
+    if q < 0.0 || r < 0.0 || q >= rules.width as f32 || r >= rules.height as f32 {
+        return None;
+    }
+    Some(rules.cell(q as usize, r as usize))
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[test]
+    fn centers_hit_their_own_cells() {
+        let rect = Rect::from_w_h(600.0, 600.0);
+        let rules = Rules::hex();
+        (0..rules.size()).for_each(|cell| {
+            let point = center(&rect, &rules, cell);
+            assert_eq!(cell_at(&rect, &rules, point), Some(cell));
+        });
+    }
+    #[test]
+    fn corners_of_the_window_are_off_the_board() {
+        let rect = Rect::from_w_h(600.0, 600.0);
+        let rules = Rules::hex();
+        assert_eq!(cell_at(&rect, &rules, pt2(-299.0, -299.0)), None);
+        assert_eq!(cell_at(&rect, &rules, pt2(299.0, 299.0)), None);
+    }
+}
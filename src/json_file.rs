@@ -0,0 +1,43 @@
+//! Reading and writing the JSON files the app keeps between runs, like the
+//! ratings and the profiles.
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Reads the value at `path`, or starts afresh if there is none yet.
+pub fn load<T: DeserializeOwned + Default>(path: impl AsRef<Path>) -> io::Result<T> {
+    match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).map_err(io::Error::from),
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(error) => Err(error),
+    }
+}
+
+pub fn save<T: Serialize>(value: &T, path: impl AsRef<Path>) -> io::Result<()> {
+    fs::write(path, serde_json::to_string_pretty(value)?)
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use std::collections::BTreeMap;
+    #[allow(unused_imports)]
+    use std::env;
+
+    #[test]
+    fn missing_files_start_afresh() {
+        let path =
+            env::temp_dir().join(format!("json-file-{}.json", std::process::id()));
+        let empty: BTreeMap<String, u32> = load(&path).unwrap();
+        assert!(empty.is_empty());
+        let mut counts = BTreeMap::new();
+        counts.insert("ann".to_string(), 3);
+        save(&counts, &path).unwrap();
+        let loaded: BTreeMap<String, u32> = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, counts);
+    }
+}
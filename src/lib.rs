@@ -0,0 +1,28 @@
+pub mod analysis;
+pub mod animation;
+pub mod announce;
+pub mod board;
+pub mod board_display;
+pub mod brain;
+pub mod clock;
+pub mod config;
+pub mod eval;
+pub mod field;
+pub mod games;
+pub mod hex;
+pub mod json_file;
+pub mod lobby;
+pub mod model;
+pub mod net;
+pub mod player;
+pub mod profiles;
+pub mod protocol;
+pub mod ratings;
+pub mod rules;
+pub mod screen;
+pub mod session;
+pub mod setup;
+pub mod svg;
+pub mod theme;
+pub mod widget;
+pub use field::Field;
@@ -0,0 +1,204 @@
+//! The window's side of the game server: finding, creating and playing games
+//! there, over the WebSocket protocol in `protocol`.
+use crate::board::{Board, BoardState};
+use crate::brain;
+use crate::field::Field;
+use crate::games::GameState;
+use crate::player::Player;
+use crate::protocol::{Reply, Request};
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+use tungstenite::Message;
+
+pub const DEFAULT_ADDRESS: &str = "ws://127.0.0.1:9001";
+
+/// How often the connection stops listening to pass on requests.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A connection to the server, talked to from a background thread so the
+/// window never waits on the network, not even to connect.
+#[derive(Debug)]
+pub struct Lobby {
+    requests: Sender<Request>,
+    /// Replies, or why the server couldn't be reached.
+    replies: Receiver<Result<Reply, String>>,
+    /// Set once the server has gone away.
+    pub closed: bool,
+    /// Why the server couldn't be reached, if it never was.
+    pub failure: Option<String>,
+}
+
+impl Lobby {
+    /// Starts connecting to `address`. Requests sent in the meantime go out
+    /// once the connection is made.
+    pub fn connect(address: &str) -> Lobby {
+        let address = address.to_string();
+        let (requests, outgoing) = mpsc::channel::<Request>();
+        let (incoming, replies) = mpsc::channel();
+        thread::spawn(move || -> tungstenite::Result<()> {
+            let connected =
+                tungstenite::connect(address.as_str()).and_then(|(socket, _)| {
+                    socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+                    Ok(socket)
+                });
+            let mut socket = match connected {
+                Ok(socket) => socket,
+                Err(error) => {
+                    let _ = incoming.send(Err(error.to_string()));
+                    return Ok(());
+                }
+            };
+            loop {
+                match socket.read_message() {
+                    Ok(Message::Text(text)) => {
+                        // anything unreadable came from a newer server
+                        if let Ok(reply) = serde_json::from_str(&text) {
+                            if incoming.send(Ok(reply)).is_err() {
+                                return socket.close(None);
+                            }
+                        }
+                    }
+                    Ok(Message::Close(_)) => return Ok(()),
+                    Ok(_) => {}
+                    Err(tungstenite::Error::Io(ref error))
+                        if error.kind() == io::ErrorKind::WouldBlock
+                            || error.kind() == io::ErrorKind::TimedOut => {}
+                    Err(error) => return Err(error),
+                }
+                loop {
+                    match outgoing.try_recv() {
+                        Ok(request) => {
+                            let text = serde_json::to_string(&request).unwrap();
+                            socket.write_message(Message::Text(text))?;
+                        }
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => return socket.close(None),
+                    }
+                }
+            }
+        });
+        Lobby {
+            requests,
+            replies,
+            closed: false,
+            failure: None,
+        }
+    }
+
+    pub fn send(&mut self, request: Request) {
+        if self.requests.send(request).is_err() {
+            self.closed = true;
+        }
+    }
+
+    /// The next reply that has come in, if any.
+    pub fn poll(&mut self) -> Option<Reply> {
+        match self.replies.try_recv() {
+            Ok(Ok(reply)) => Some(reply),
+            Ok(Err(failure)) => {
+                self.failure = Some(failure);
+                self.closed = true;
+                None
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.closed = true;
+                None
+            }
+        }
+    }
+}
+
+/// Makes `board` show the position the server sent.
+pub fn mirror(board: &mut Board, state: &GameState) {
+    board.rules = state.variant.rules();
+    board.board = state.cells.clone();
+    board.queues = Default::default();
+    for &cell in &state.marks {
+        match board.board.get(cell) {
+            Some(Field::X) => board.queues[Player::Player1.index()].push_back(cell),
+            Some(Field::O) => board.queues[Player::Player2.index()].push_back(cell),
+            _ => {}
+        }
+    }
+    board.current_player = state.to_move;
+    board.selected = None;
+    // the server repeats itself when someone sits down, which isn't a move
+    let key = brain::position_key(&board.board, &board.queues, board.current_player);
+    if board.history.last() != Some(&key) {
+        board.history.push(key);
+    }
+    board.state = match state.winner {
+        Some(player) => BoardState::Winner(player, state.winning_line.clone()),
+        None if state.finished => BoardState::Tie,
+        None => BoardState::InGame,
+    };
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::board::test::board;
+    #[allow(unused_imports)]
+    use crate::games::Game;
+    #[allow(unused_imports)]
+    use crate::player::Player;
+    #[allow(unused_imports)]
+    use crate::rules::{Move, Variant};
+    #[allow(unused_imports)]
+    use crate::setup::Setup;
+
+    #[test]
+    fn mirrored_board_matches_the_game() {
+        let mut game = Game::new(Variant::Classic, Setup::Empty, None);
+        for &cell in &[0, 3, 1, 4, 2] {
+            let side = game.board.current_player;
+            game.play(side, Move::Place(cell)).unwrap();
+        }
+        let mut board = board(Variant::Gravity.rules());
+        mirror(&mut board, &game.state());
+        assert_eq!(board.board, game.board.board);
+        assert_eq!(board.rules, Variant::Classic.rules());
+        assert_eq!(board.history.len(), 2);
+        mirror(&mut board, &game.state());
+        assert_eq!(board.history.len(), 2);
+        assert_eq!(
+            board.state,
+            BoardState::Winner(Player::Player1, vec![0, 1, 2])
+        );
+    }
+    #[test]
+    fn mirrored_marks_vanish_in_the_servers_order() {
+        let mut game = Game::new(Variant::Infinite, Setup::Empty, None);
+        // X's marks go down in 8, 0 and 1, so the one in 8 vanishes first
+        for &cell in &[8, 4, 0, 5, 1, 2] {
+            let side = game.board.current_player;
+            game.play(side, Move::Place(cell)).unwrap();
+        }
+        let mut board = board(Variant::Infinite.rules());
+        mirror(&mut board, &game.state());
+        assert_eq!(board.queues, game.board.queues);
+        assert_eq!(board.vanishing(), vec![8, 4]);
+        board.play(Move::Place(6));
+        game.play(Player::Player1, Move::Place(6)).unwrap();
+        assert_eq!(board.board, game.board.board);
+    }
+    #[test]
+    fn connecting_does_not_wait_for_the_server() {
+        // nothing listens on a port that was just given back
+        let address = {
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("ws://{}", listener.local_addr().unwrap())
+        };
+        let mut lobby = Lobby::connect(&address);
+        lobby.send(Request::List);
+        while !lobby.closed {
+            assert_eq!(lobby.poll(), None);
+            thread::sleep(POLL_INTERVAL);
+        }
+        assert!(lobby.failure.is_some());
+    }
+}
@@ -1,76 +1,144 @@
 use nannou::prelude::*;
-mod board;
-mod board_display;
-mod brain;
-mod eval;
-mod field;
-mod model;
-mod player;
-use board::Board;
-pub use field::Field;
-use model::{GameMode, Model, PlayerMode};
+use std::time::Duration;
+use tic_tac_toe::animation::Animation;
+use tic_tac_toe::announce::Announcer;
+use tic_tac_toe::board::Board;
+use tic_tac_toe::clock::TimeControl;
+use tic_tac_toe::config::Config;
+use tic_tac_toe::lobby;
+use tic_tac_toe::model::{Model, PlayerMode};
+use tic_tac_toe::net;
+use tic_tac_toe::profiles::{self, Profiles};
+use tic_tac_toe::ratings::{self, Ratings};
+use tic_tac_toe::screen::Screen;
+use tic_tac_toe::session::Score;
+use tic_tac_toe::setup::Setup;
+use tic_tac_toe::theme::{self, Theme};
 
 fn main() {
-    nannou::app(model).view(view).run();
+    nannou::app(model).update(update).view(view).run();
 }
 
 fn model(app: &App) -> Model {
+    let config = Config::load(Config::path()).unwrap_or_else(|error| {
+        eprintln!("could not use the settings in {}", error);
+        std::process::exit(1);
+    });
     app.new_window()
-        .with_dimensions(600, 600)
+        .with_dimensions(config.window.width, config.window.height)
         .event(window_event)
         .build()
         .unwrap();
-    Model {
-        board: Board::new(app.window_rect(), PlayerMode::PlayUndecided),
-        mode: GameMode::Waiting,
+    let variant = config.game.variant;
+    let themes = theme::load_all(&config.theme.directory);
+    let animation = Animation::new(config.animation);
+    let announcer = config.accessibility.announce.as_ref().and_then(|target| {
+        Announcer::open(target)
+            .map_err(|error| eprintln!("could not announce to {}: {}", target, error))
+            .ok()
+    });
+    let mut model = Model {
+        board: Board::new(
+            app.window_rect(),
+            PlayerMode::PlayUndecided,
+            variant.rules(),
+        ),
+        screen: Screen::Profiles,
         player_mode: PlayerMode::PlayUndecided,
+        variant,
+        setup: Setup::default(),
+        address: std::env::args()
+            .nth(1)
+            .unwrap_or_else(|| net::DEFAULT_ADDRESS.to_string()),
+        door: None,
+        peer: None,
+        connecting: None,
+        difficulty: config.game.difficulty,
+        best_of: config.game.best_of,
+        server: std::env::args()
+            .nth(2)
+            .unwrap_or_else(|| lobby::DEFAULT_ADDRESS.to_string()),
+        lobby: None,
+        open_games: vec![],
+        code: String::new(),
+        status: String::new(),
+        seat: None,
+        rival: None,
+        ratings: Ratings::load(ratings::DEFAULT_PATH).unwrap_or_else(|error| {
+            eprintln!("could not read {}: {}", ratings::DEFAULT_PATH, error);
+            Ratings::default()
+        }),
+        profiles: Profiles::load(profiles::DEFAULT_PATH).unwrap_or_else(|error| {
+            eprintln!("could not read {}: {}", profiles::DEFAULT_PATH, error);
+            Profiles::default()
+        }),
+        player: String::new(),
+        partner: None,
+        typed_name: String::new(),
+        recorded: false,
+        time_control: TimeControl::default(),
+        clock: None,
+        thought: Duration::from_secs(0),
+        config,
+        focus: None,
+        announcer,
+        theme: Theme::light(),
+        themes,
+        animation,
+        scale: 1.0,
+        mouse: pt2(0.0, 0.0),
+        held: false,
+        score: Score::default(),
+        analysis: None,
+        series: None,
+    };
+    model.layout(&app.window_rect());
+    let name = model.config.theme_name().to_string();
+    if !model.use_theme(&name) {
+        eprintln!("there is no theme called {:?}", name);
+        model.use_theme("light");
     }
+    model
+}
+
+fn update(app: &App, model: &mut Model, update: Update) {
+    model.layout(&app.window_rect());
+    model.tick_clock(update.since_last);
+    model.computer_turn();
+    model.poll_network(&app.window_rect());
+    model.poll_lobby(&app.window_rect());
+    model.poll_analysis();
+    model.end_game();
+    model.record_game();
+    model.announce();
+    model.animate(update.since_last);
 }
 
 fn view(app: &App, model: &Model, frame: &Frame) {
-    frame.clear(SKYBLUE);
+    frame.clear(model.theme.background.0);
     let draw = app.draw();
     model.display(&draw, &app.window_rect());
     draw.to_frame(app, &frame).unwrap();
 }
 
 fn window_event(app: &App, model: &mut Model, event: WindowEvent) {
-    if model.mode == GameMode::SinglePlayer(PlayerMode::PlayX)
-        || model.mode == GameMode::SinglePlayer(PlayerMode::PlayO)
-    {
-        if model.board.player_2 == model.board.current_player {
-            model.board.computer_move();
-        }
-    }
     match event {
-        MousePressed(_button) => match &model.mode {
-            GameMode::Waiting => {
-                model.mode = model.check_mode(&app.window_rect(), app.mouse.position());
-            }
-            GameMode::SinglePlayer(x) => match x {
-                PlayerMode::PlayUndecided => {
-                    model.mode = model
-                        .check_player_mode(&app.window_rect(), app.mouse.position());
-                    model.player_mode =
-                        if model.mode == GameMode::SinglePlayer(PlayerMode::PlayX) {
-                            PlayerMode::PlayX
-                        } else {
-                            PlayerMode::PlayO
-                        };
-                    model.board = Board::new(app.window_rect(), model.player_mode);
-                }
-                _ => {
-                    model.check_new(app);
-                }
-            },
-            _ => {
-                model.check_new(app);
-            }
-        },
+        MouseMoved(position) => {
+            model.mouse = position;
+        }
+        MousePressed(_button) => {
+            model.held = true;
+            model.click(&app.window_rect(), app.mouse.position());
+        }
+        MouseReleased(_button) => {
+            model.held = false;
+        }
+        KeyPressed(key) => {
+            model.press_key(&app.window_rect(), key, app.keys.mods.shift);
+        }
         Resized(_size) => {
-            model.board = Board::new(app.window_rect(), PlayerMode::PlayUndecided);
+            model.layout(&app.window_rect());
         }
-
         _ => {}
     }
 }
@@ -1,13 +1,98 @@
+use crate::analysis::{self, Analysis};
+use crate::animation::Animation;
+use crate::announce::Announcer;
 use crate::board::{Board, BoardState};
+use crate::brain::Difficulty;
+use crate::clock::{self, Clock, TimeControl};
+use crate::config::Config;
+use crate::lobby::{self, Lobby};
+use crate::net::{Connecting, Door, Peer, Role, Visitor};
+use crate::player::Player;
+use crate::profiles::{self, PlayMode, Profiles};
+use crate::protocol::{Listing, Reply, Request};
+use crate::ratings::{self, Ratings};
+use crate::rules::Variant;
+use crate::screen::{Event, Opponent, Screen};
+use crate::session::{self, Score, Series};
+use crate::setup::Setup;
+use crate::theme::{ButtonStyle, Theme};
+use crate::widget::{self, Button, Menu};
 use nannou::prelude::*;
+use std::io;
+use std::time::{Duration, Instant};
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum GameMode {
-    SinglePlayer(PlayerMode),
-    MultiPlayer,
-    Waiting,
+/// The lobby's buttons, laid out three to a row.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum LobbyButton {
+    HotSeat,
+    QuickMatch,
+    PlayBot,
+    NewPublic,
+    NewPrivate,
+    Difficulty,
+    Refresh,
+    JoinCode,
+    Back,
 }
 
+const LOBBY_BUTTONS: [[LobbyButton; 3]; 3] = [
+    [
+        LobbyButton::HotSeat,
+        LobbyButton::QuickMatch,
+        LobbyButton::PlayBot,
+    ],
+    [
+        LobbyButton::NewPublic,
+        LobbyButton::NewPrivate,
+        LobbyButton::Difficulty,
+    ],
+    [
+        LobbyButton::Refresh,
+        LobbyButton::JoinCode,
+        LobbyButton::Back,
+    ],
+];
+
+/// What pressing a button does.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Action {
+    SinglePlayer,
+    Multiplayer,
+    Host,
+    Join,
+    Watch,
+    Ratings,
+    Profile,
+    Settings,
+    Variant,
+    Setup,
+    Clock,
+    Difficulty,
+    MatchLength,
+    Partner,
+    Theme,
+    PlayFirst,
+    PlaySecond,
+    Lobby(LobbyButton),
+    /// Joins the open game in this row of the list.
+    JoinListing(usize),
+    /// Plays as the profile in this row of the list.
+    PickProfile(usize),
+    CreateProfile,
+    Export,
+    SwitchProfile,
+    Rematch,
+    /// A rematch against the computer, on the other side.
+    SwapSides,
+    Analyze,
+    Previous,
+    Next,
+    Back,
+}
+
+/// Digits in a private game's join code.
+const CODE_LENGTH: usize = 6;
+
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum PlayerMode {
     PlayX,
@@ -18,175 +103,1462 @@ pub enum PlayerMode {
 #[derive(Debug)]
 pub struct Model {
     pub board: Board,
-    pub mode: GameMode,
+    pub screen: Screen,
     pub player_mode: PlayerMode,
+    pub variant: Variant,
+    pub setup: Setup,
+    /// Where to host or join network games.
+    pub address: String,
+    pub door: Option<Door>,
+    pub peer: Option<Peer>,
+    /// The host being joined or watched, until it has greeted back.
+    pub connecting: Option<Connecting>,
+    /// How hard the computer plays, offline and on the server.
+    pub difficulty: Difficulty,
+    /// The game server the lobby connects to.
+    pub server: String,
+    pub lobby: Option<Lobby>,
+    pub open_games: Vec<Listing>,
+    /// The join code typed so far.
+    pub code: String,
+    /// The lobby's last news, shown above its buttons.
+    pub status: String,
+    /// The server game being played and this side of it.
+    pub seat: Option<(u64, Player)>,
+    /// The other player's name in the server game, once they have given it.
+    pub rival: Option<String>,
+    pub ratings: Ratings,
+    pub profiles: Profiles,
+    /// The profile playing, which games are rated and counted under.
+    pub player: String,
+    /// The profile playing O in hot seat games, so they are counted and
+    /// rated too.
+    pub partner: Option<String>,
+    /// The name of a new profile, as typed so far.
+    pub typed_name: String,
+    /// Whether the finished game on the board has been counted.
+    pub recorded: bool,
+    pub time_control: TimeControl,
+    /// The running clocks of a timed game in this window.
+    pub clock: Option<Clock>,
+    /// How long the computer spent on its last move, already taken off its
+    /// clock rather than the next frame's.
+    pub thought: Duration,
+    pub config: Config,
+    /// The button picked with the keyboard on a menu screen, once the arrow
+    /// keys have been used there.
+    pub focus: Option<usize>,
+    /// Where game events go as text, if anywhere.
+    pub announcer: Option<Announcer>,
+    /// The theme in use, with any colors the settings replace.
+    pub theme: Theme,
+    /// The built in themes and the user's own, in the order the menu button
+    /// goes through them.
+    pub themes: Vec<Theme>,
+    pub animation: Animation,
+    /// How much bigger than `DESIGN_SIZE` the window is, for text.
+    pub scale: f32,
+    /// Where the mouse is, to light up the button under it.
+    pub mouse: Point2,
+    /// Whether a mouse button is down.
+    pub held: bool,
+    /// Games finished in this window since the app started.
+    pub score: Score,
+    /// The finished game being gone over, on the analysis screen.
+    pub analysis: Option<Analysis>,
+    /// How many games a match against the computer or in the hot seat is
+    /// the best of. One plays single games.
+    pub best_of: u32,
+    /// The match being played, which carries on from board to board until
+    /// it is over or left.
+    pub series: Option<Series>,
 }
 
+/// Letters in a profile name.
+const NAME_LENGTH: usize = 16;
+
 impl Model {
-    pub fn check_new(&mut self, app: &App) {
+    /// Handles a click at `mouse` on whatever screen is showing. Clicks
+    /// between the buttons of a menu do nothing.
+    pub fn click(&mut self, rect: &Rect, mouse: Point2) {
+        let clicked = self
+            .buttons(rect)
+            .into_iter()
+            .find(|(_, button)| button.contains(mouse));
+        if let Some((action, _)) = clicked {
+            return self.act(rect, action);
+        }
+        match self.screen {
+            Screen::Hosting => self.back(rect),
+            Screen::Playing(_) => {
+                let cell = self.board.cell_at(mouse);
+                self.choose_cell(cell);
+            }
+            _ => {}
+        }
+    }
+    /// Picks `cell` in the game being played.
+    fn choose_cell(&mut self, cell: Option<usize>) {
         if self.board.state != BoardState::InGame {
-            self.mode = GameMode::Waiting;
-            self.board = Board::new(app.window_rect(), self.player_mode);
+            return;
+        }
+        match self.screen {
+            Screen::Playing(Opponent::Online) => self.check_online(cell),
+            Screen::Playing(Opponent::Server) => self.check_matched(cell),
+            Screen::Playing(_) => self.board.register_cell(cell),
+            _ => {}
+        }
+    }
+    /// Handles a key press: the menu key goes back from anywhere, number and
+    /// arrow keys play in a game, and elsewhere the arrow keys move the focus
+    /// between buttons and the confirm key presses the focused one.
+    pub fn press_key(&mut self, rect: &Rect, key: Key, shift: bool) {
+        let keys = self.config.keys;
+        if key == keys.menu.0 {
+            return self.back(rect);
+        }
+        match self.screen {
+            Screen::Playing(_) => return self.play_key(key),
+            Screen::Analysis(_) if key == keys.left.0 => return self.step(-1),
+            Screen::Analysis(_) if key == keys.right.0 => return self.step(1),
+            _ if key == keys.confirm.0 => {}
+            Screen::Lobby => self.type_code(key),
+            Screen::Profiles => self.type_name(key, shift),
+            _ => {}
+        }
+        let buttons = self.buttons(rect);
+        let focus = self.focus.filter(|&index| index < buttons.len());
+        let last = buttons.len().saturating_sub(1);
+        if key == keys.up.0 || key == keys.left.0 || (key == Key::Tab && shift) {
+            self.focus = Some(focus.map_or(last, |index| index.saturating_sub(1)));
+        } else if key == keys.down.0 || key == keys.right.0 || key == Key::Tab {
+            self.focus = Some(focus.map_or(0, |index| (index + 1).min(last)));
+        } else if key == keys.confirm.0 {
+            match focus {
+                Some(index) => self.act(rect, buttons[index].0),
+                None if self.screen == Screen::Profiles => self.create_profile(),
+                None => {}
+            }
+        }
+        if buttons.is_empty() {
+            self.focus = None;
+        }
+    }
+    /// Number keys play a cell straight away; the arrow keys move the cursor
+    /// and the confirm key plays where it is.
+    fn play_key(&mut self, key: Key) {
+        let keys = self.config.keys;
+        let steps = [
+            (keys.left.0, -1, 0),
+            (keys.right.0, 1, 0),
+            (keys.up.0, 0, -1),
+            (keys.down.0, 0, 1),
+        ];
+        if let Some(&(_, columns, rows)) = steps.iter().find(|step| step.0 == key) {
+            return self.board.move_cursor(columns, rows);
+        }
+        let cell = if key == keys.confirm.0 {
+            self.board.cursor
         } else {
-            self.board.register_click(&app);
-        }
-    }
-    pub fn check_mode(&self, rect: &Rect, mouse: Point2) -> GameMode {
-        let x_single_player = rect.left() / 3.0;
-        let y_single_player = 0.0;
-        let x_multi_player = rect.right() / 3.0;
-        let y_multi_player = 0.0;
-        let width = 150.0;
-        let height = width / 1.618;
-        let single_player =
-            Rect::from_x_y_w_h(x_single_player, y_single_player, width, height);
-        let multi_player =
-            Rect::from_x_y_w_h(x_multi_player, y_multi_player, width, height);
-
-        if single_player.contains(mouse) {
-            return GameMode::SinglePlayer(PlayerMode::PlayUndecided);
-        } else if multi_player.contains(mouse) {
-            return GameMode::MultiPlayer;
-        };
-        GameMode::Waiting
-    }
-    pub fn check_player_mode(&self, rect: &Rect, mouse: Point2) -> GameMode {
-        let x_single_player = rect.left() / 3.0;
-        let y_single_player = 0.0;
-        let x_multi_player = rect.right() / 3.0;
-        let y_multi_player = 0.0;
-        let width = 150.0;
-        let height = width / 1.618;
-        let play_first =
-            Rect::from_x_y_w_h(x_single_player, y_single_player, width, height);
-        let play_second =
-            Rect::from_x_y_w_h(x_multi_player, y_multi_player, width, height);
-
-        if play_first.contains(mouse) {
-            return GameMode::SinglePlayer(PlayerMode::PlayX);
-        } else if play_second.contains(mouse) {
-            return GameMode::SinglePlayer(PlayerMode::PlayO);
+            match digit(key) {
+                Some(digit) => self.board.digit_cell(digit as usize, keys.numpad),
+                None => return,
+            }
+        };
+        self.choose_cell(cell);
+    }
+    /// Follows `event` to the screen it leads to, see `Screen::next`.
+    fn go(&mut self, event: Event) {
+        let next = self.screen.next(event);
+        if next != self.screen {
+            self.screen = next;
+            self.focus = None;
+        }
+    }
+    /// Goes back from whatever is going on, leaving any network game on the
+    /// way. Before a profile is picked there is nowhere to go back to, and an
+    /// analysis goes back to the finished game.
+    pub fn back(&mut self, rect: &Rect) {
+        match self.screen {
+            Screen::Profiles if self.player.is_empty() => return,
+            Screen::Hosting
+            | Screen::Playing(Opponent::Online)
+            | Screen::GameOver(Opponent::Online) => self.leave_network(),
+            Screen::Lobby => self.lobby = None,
+            _ => {}
+        }
+        self.status.clear();
+        self.typed_name.clear();
+        self.analysis = None;
+        let game = self.screen.opponent().is_some();
+        self.go(Event::Back);
+        if game && self.screen.opponent().is_none() {
+            self.series = None;
+            self.board = self.new_board(rect);
+        }
+        if self.screen == Screen::Lobby {
+            self.enter_lobby();
+        }
+    }
+    /// The buttons of the screen showing and what each does, in the order
+    /// the arrow keys visit them.
+    fn buttons(&self, rect: &Rect) -> Vec<(Action, Button)> {
+        match self.screen {
+            Screen::MainMenu => {
+                let [single_player, multi_player] = Model::choice_buttons(rect);
+                let network = Model::network_buttons(rect);
+                let profile = Model::profile_button(rect);
+                vec![
+                    (Action::Profile, Button::new(profile, &*self.player)),
+                    (
+                        Action::Settings,
+                        Button::new(Model::settings_button(rect), "Settings"),
+                    ),
+                    (
+                        Action::Ratings,
+                        Button::new(Model::ratings_button(rect), "Ratings"),
+                    ),
+                    (Action::Host, Button::new(network[0], "Host Game")),
+                    (Action::Join, Button::new(network[1], "Join Game")),
+                    (Action::Watch, Button::new(network[2], "Watch Game")),
+                    (
+                        Action::SinglePlayer,
+                        Button::new(single_player, "Single Player"),
+                    ),
+                    (
+                        Action::Multiplayer,
+                        Button::new(multi_player, "Multiplayer"),
+                    ),
+                ]
+            }
+            Screen::SideSelect => {
+                let [play_first, play_second] = Model::choice_buttons(rect);
+                vec![
+                    (Action::PlayFirst, Button::new(play_first, "Play First")),
+                    (Action::PlaySecond, Button::new(play_second, "Play Second")),
+                ]
+            }
+            Screen::Settings => {
+                let s = scale(rect);
+                let labels = [
+                    format!("Board: {}", self.variant.to_string()),
+                    format!("Start: {}", self.setup.to_string()),
+                    format!("Clock: {}", self.time_control.to_string()),
+                    format!("Bot: {}", self.difficulty.to_string()),
+                    format!("Match: {}", session::length_name(self.best_of)),
+                    format!(
+                        "Hot Seat O: {}",
+                        self.partner.as_deref().unwrap_or("Guest")
+                    ),
+                    format!("Theme: {}", self.theme.name),
+                    "Back".to_string(),
+                ];
+                let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+                let size = vec2(320.0 * s, 50.0 * s);
+                let menu = Menu::vertical(pt2(0.0, -20.0 * s), size, 10.0 * s, &labels);
+                let actions = [
+                    Action::Variant,
+                    Action::Setup,
+                    Action::Clock,
+                    Action::Difficulty,
+                    Action::MatchLength,
+                    Action::Partner,
+                    Action::Theme,
+                    Action::Back,
+                ];
+                actions.iter().cloned().zip(menu.buttons).collect()
+            }
+            Screen::Lobby => {
+                let buttons =
+                    LOBBY_BUTTONS.iter().enumerate().flat_map(|(row, buttons)| {
+                        buttons.iter().enumerate().map(move |(column, &button)| {
+                            let area = Model::lobby_button(rect, row, column);
+                            (
+                                Action::Lobby(button),
+                                Button::new(area, self.lobby_label(button)),
+                            )
+                        })
+                    });
+                let listings = self
+                    .open_games
+                    .iter()
+                    .take(self.visible_listings(rect))
+                    .enumerate()
+                    .map(|(index, listing)| {
+                        let label = format!(
+                            "Game {}: {}, join as {}",
+                            listing.game,
+                            listing.variant.to_string(),
+                            listing.side.get_sigil().to_string()
+                        );
+                        let area = Model::listing_row(rect, index);
+                        (Action::JoinListing(index), Button::new(area, label))
+                    });
+                buttons.chain(listings).collect()
+            }
+            Screen::Profiles => {
+                let shown = self.visible_profiles(rect);
+                self.profiles
+                    .players
+                    .iter()
+                    .take(shown)
+                    .enumerate()
+                    .map(|(index, (name, stats))| {
+                        let label = format!(
+                            "{}   ({} games, {} won)",
+                            name, stats.total.games, stats.total.wins
+                        );
+                        let area = Model::profile_row(rect, index);
+                        (Action::PickProfile(index), Button::new(area, label))
+                    })
+                    .chain(Some((
+                        Action::CreateProfile,
+                        Button::new(Model::create_button(rect), "Create"),
+                    )))
+                    .collect()
+            }
+            Screen::Ratings | Screen::Stats => {
+                let [action, back] = Model::footer_buttons(rect);
+                let (own, label) = match self.screen {
+                    Screen::Ratings => (Action::Export, "Export"),
+                    _ => (Action::SwitchProfile, "Switch"),
+                };
+                vec![
+                    (own, Button::new(action, label)),
+                    (Action::Back, Button::new(back, "Back")),
+                ]
+            }
+            Screen::GameOver(opponent) => {
+                let mut actions = vec![];
+                let between_games = self.series.iter().any(|series| !series.over());
+                let rematch = match self.series {
+                    Some(_) if between_games => "Next Game",
+                    Some(_) => "New Match",
+                    None => "Rematch",
+                };
+                if opponent == Opponent::Computer || opponent == Opponent::HotSeat {
+                    actions.push((Action::Rematch, rematch));
+                }
+                if opponent == Opponent::Computer && !between_games {
+                    actions.push((Action::SwapSides, "Swap Sides"));
+                }
+                if analysis::available(&self.board) {
+                    actions.push((Action::Analyze, "Analyze"));
+                }
+                match opponent {
+                    Opponent::Server => actions.push((Action::Back, "Lobby")),
+                    _ => actions.push((Action::Back, "Main Menu")),
+                }
+                Model::panel_buttons(rect, &actions)
+            }
+            Screen::Analysis(_) => {
+                let actions = [
+                    (Action::Previous, "Previous"),
+                    (Action::Next, "Next"),
+                    (Action::Back, "Back"),
+                ];
+                Model::panel_buttons(rect, &actions)
+            }
+            _ => vec![],
+        }
+    }
+    /// Does what a button does, whether it was clicked or picked with the
+    /// keyboard.
+    fn act(&mut self, rect: &Rect, action: Action) {
+        match action {
+            Action::SinglePlayer => self.go(Event::PlayComputer),
+            Action::Multiplayer => {
+                self.go(Event::OpenLobby);
+                self.enter_lobby();
+            }
+            Action::Host => self.host(),
+            Action::Join => self.join(),
+            Action::Watch => self.watch(),
+            Action::Ratings => self.go(Event::OpenRatings),
+            Action::Profile => self.go(Event::OpenStats),
+            Action::Settings => self.go(Event::OpenSettings),
+            Action::Variant => {
+                self.variant = self.variant.next();
+                if self.setup.check(&self.variant.rules()).is_err() {
+                    self.setup = Setup::Empty;
+                }
+                self.board = self.new_board(rect);
+            }
+            Action::Setup => {
+                self.setup = self
+                    .setup
+                    .next(random_range(0, 10_000), &self.variant.rules());
+                self.board = self.new_board(rect);
+            }
+            Action::Clock => self.time_control = self.time_control.next(),
+            Action::Difficulty => self.difficulty = self.difficulty.next(),
+            Action::MatchLength => self.best_of = session::next_length(self.best_of),
+            Action::Partner => self.next_partner(),
+            Action::Theme => self.next_theme(),
+            Action::PlayFirst => self.choose_side(rect, PlayerMode::PlayX),
+            Action::PlaySecond => self.choose_side(rect, PlayerMode::PlayO),
+            Action::Lobby(button) => self.press_lobby(rect, button),
+            Action::JoinListing(index) => {
+                let game = self.open_games[index].game;
+                self.request(Request::Join {
+                    game: Some(game),
+                    code: None,
+                    name: self.name(),
+                });
+            }
+            Action::PickProfile(index) => {
+                let name = self.profiles.players.keys().nth(index).unwrap().clone();
+                self.pick_profile(name);
+            }
+            Action::CreateProfile => self.create_profile(),
+            Action::Export => self.export_ratings(),
+            Action::SwitchProfile => self.go(Event::SwitchProfile),
+            Action::Rematch => self.rematch(rect),
+            Action::SwapSides => {
+                self.player_mode = match self.player_mode {
+                    PlayerMode::PlayX => PlayerMode::PlayO,
+                    _ => PlayerMode::PlayX,
+                };
+                self.rematch(rect);
+            }
+            Action::Analyze => {
+                self.analysis = Analysis::new(&self.board);
+                if self.analysis.is_some() {
+                    self.go(Event::Analyze);
+                }
+            }
+            Action::Previous => self.step(-1),
+            Action::Next => self.step(1),
+            Action::Back => self.back(rect),
+        }
+    }
+    /// Draws the screen's buttons, lit up under the mouse. Rows of lists
+    /// are written smaller, on the row color.
+    fn show_buttons(&self, draw: &app::Draw, rect: &Rect) {
+        let row = ButtonStyle {
+            fill: self.theme.row,
+            label: self.theme.text,
+            border_weight: 0.0,
+            ..self.theme.button
+        };
+        for (action, button) in self.buttons(rect) {
+            let state = button.state(self.mouse, self.held);
+            match action {
+                Action::JoinListing(_) | Action::PickProfile(_) => {
+                    button.draw(draw, &row, self.font(self.config.fonts.status), state)
+                }
+                _ => {
+                    let size = self.font(self.config.fonts.button);
+                    button.draw(draw, &self.theme.button, size, state)
+                }
+            }
+        }
+    }
+    /// Outlines the button the keyboard has picked.
+    fn show_focus(&self, draw: &app::Draw, rect: &Rect) {
+        let area = match self
+            .focus
+            .and_then(|i| self.buttons(rect).get(i).map(|(_, button)| button.area))
+        {
+            Some(area) => area,
+            None => return,
+        };
+        widget::outline(draw, area, 4.0, 3.0, self.theme.focus.0);
+    }
+    /// The two buttons side by side in the middle of the main menu and of
+    /// the choice of side.
+    fn choice_buttons(rect: &Rect) -> [Rect; 2] {
+        let size = big_button(rect);
+        let places = widget::row(pt2(0.0, 0.0), size, rect.w() / 3.0 - size.x, 2);
+        [places[0], places[1]]
+    }
+    /// The main menu's row of Host, Join and Watch.
+    fn network_buttons(rect: &Rect) -> Vec<Rect> {
+        let size = big_button(rect);
+        let center = pt2(0.0, rect.top() / 2.0);
+        widget::row(center, size, rect.w() / 3.0 - size.x, 3)
+    }
+    fn ratings_button(rect: &Rect) -> Rect {
+        let s = scale(rect);
+        Rect::from_x_y_w_h(
+            rect.right() - 80.0 * s,
+            rect.top() - 30.0 * s,
+            140.0 * s,
+            40.0 * s,
+        )
+    }
+    /// The box over the bottom of a finished game, with the result and
+    /// what to do next.
+    fn panel(rect: &Rect) -> Rect {
+        let s = scale(rect);
+        Rect::from_x_y_w_h(0.0, rect.bottom() + 85.0 * s, 580.0 * s, 160.0 * s)
+    }
+    /// A row of buttons along the bottom of the panel, in the order given.
+    fn panel_buttons(rect: &Rect, actions: &[(Action, &str)]) -> Vec<(Action, Button)> {
+        let s = scale(rect);
+        let center = pt2(0.0, Model::panel(rect).bottom() + 35.0 * s);
+        let size = vec2(130.0 * s, 45.0 * s);
+        let places = widget::row(center, size, 10.0 * s, actions.len());
+        actions
+            .iter()
+            .zip(places)
+            .map(|(&(action, label), area)| (action, Button::new(area, label)))
+            .collect()
+    }
+    /// A screen's own button and Back, along the bottom.
+    fn footer_buttons(rect: &Rect) -> [Rect; 2] {
+        let s = scale(rect);
+        let center = pt2(0.0, rect.bottom() + 40.0 * s);
+        let size = vec2(150.0 * s, 50.0 * s);
+        let places = widget::row(center, size, rect.w() / 2.0 - size.x, 2);
+        [places[0], places[1]]
+    }
+    /// Counts a finished game for the current profile, and for the partner
+    /// in the hot seat, and rates it when both sides are named. Watched
+    /// games aren't counted.
+    pub fn record_game(&mut self) {
+        if self.board.state == BoardState::InGame {
+            self.recorded = false;
+            return;
+        }
+        if self.recorded {
+            return;
+        }
+        self.recorded = true;
+        let played = match self.screen.opponent() {
+            Some(Opponent::Computer) => {
+                Some((PlayMode::SinglePlayer, self.board.player_1))
+            }
+            Some(Opponent::HotSeat) => Some((PlayMode::HotSeat, Player::Player1)),
+            Some(Opponent::Online) => self
+                .peer
+                .as_ref()
+                .filter(|peer| peer.role != Role::Spectator)
+                .map(|peer| (PlayMode::Network, peer.side)),
+            Some(Opponent::Server) => {
+                self.seat.map(|(_, side)| (PlayMode::Server, side))
+            }
+            _ => None,
+        };
+        let result = &self.board.state;
+        if let Some((mode, side)) = played {
+            let moves = self.board.history.len() - 1;
+            self.profiles
+                .record(&self.player, mode, side, result, moves);
+            if let (PlayMode::HotSeat, Some(partner)) = (mode, &self.partner) {
+                self.profiles.record(partner, mode, -side, result, moves);
+            }
+            if let Err(error) = self.profiles.save(profiles::DEFAULT_PATH) {
+                eprintln!("could not save the profiles: {}", error);
+            }
+        }
+        let (side, rival) = match self.opponent_name() {
+            Some(opponent) => opponent,
+            None => return,
+        };
+        if let Some((x, o)) = ratings::pairing(&self.player, side, &rival) {
+            self.ratings.record(x, o, result);
+            if let Err(error) = self.ratings.save(ratings::DEFAULT_PATH) {
+                eprintln!("could not save the ratings: {}", error);
+            }
+        }
+    }
+    /// The current profile's side in the game on the board and who it is
+    /// playing, for those opponents with a name: the computer at its
+    /// difficulty, the hot seat partner, or the other player online.
+    fn opponent_name(&self) -> Option<(Player, String)> {
+        match self.screen.opponent()? {
+            Opponent::Computer => {
+                Some((self.board.player_1, ratings::bot_name(self.difficulty)))
+            }
+            Opponent::HotSeat => Some((Player::Player1, self.partner.clone()?)),
+            Opponent::Online => {
+                let peer = self.peer.as_ref()?;
+                if peer.role == Role::Spectator {
+                    return None;
+                }
+                Some((peer.side, peer.rival.clone()?))
+            }
+            Opponent::Server => Some((self.seat?.1, self.rival.clone()?)),
+        }
+    }
+    /// The profile to give the server, if one has been picked.
+    fn name(&self) -> Option<String> {
+        Some(self.player.clone()).filter(|name| !name.is_empty())
+    }
+    /// Cycles the hot seat partner through the other profiles, then back to
+    /// an unrated guest.
+    fn next_partner(&mut self) {
+        let others: Vec<&String> = self
+            .profiles
+            .players
+            .keys()
+            .filter(|&name| name != &self.player)
+            .collect();
+        let next = match &self.partner {
+            Some(partner) => others
+                .iter()
+                .position(|&name| name == partner)
+                .map(|i| i + 1),
+            None => Some(0),
+        };
+        self.partner = next.and_then(|i| others.get(i)).map(|&name| name.clone());
+    }
+    fn profile_button(rect: &Rect) -> Rect {
+        let s = scale(rect);
+        Rect::from_x_y_w_h(
+            rect.left() + 80.0 * s,
+            rect.top() - 30.0 * s,
+            140.0 * s,
+            40.0 * s,
+        )
+    }
+    fn profile_row(rect: &Rect, index: usize) -> Rect {
+        let s = scale(rect);
+        let y = rect.top() - (80.0 + index as f32 * 34.0) * s;
+        Rect::from_x_y_w_h(0.0, y, rect.w() / 3.0 * 2.0, 30.0 * s)
+    }
+    fn name_field(rect: &Rect) -> Rect {
+        let s = scale(rect);
+        Rect::from_x_y_w_h(0.0, rect.bottom() + 110.0 * s, 300.0 * s, 40.0 * s)
+    }
+    fn create_button(rect: &Rect) -> Rect {
+        let s = scale(rect);
+        Rect::from_x_y_w_h(0.0, rect.bottom() + 50.0 * s, 150.0 * s, 50.0 * s)
+    }
+    /// The rows of the profile list that fit above the name field.
+    fn visible_profiles(&self, rect: &Rect) -> usize {
+        let bottom = Model::name_field(rect).top();
+        (0..self.profiles.players.len())
+            .take_while(|&i| Model::profile_row(rect, i).bottom() > bottom)
+            .count()
+    }
+    fn pick_profile(&mut self, name: String) {
+        self.profiles.create(&name);
+        if let Err(error) = self.profiles.save(profiles::DEFAULT_PATH) {
+            eprintln!("could not save the profiles: {}", error);
+        }
+        self.player = name;
+        self.typed_name.clear();
+        self.start();
+    }
+    /// Opens the screen the settings start on.
+    fn start(&mut self) {
+        self.go(Event::Start(self.config.game.mode));
+        match self.screen {
+            Screen::Playing(_) => {
+                self.start_match();
+                self.board = self.new_board(&self.board.rect);
+            }
+            Screen::Lobby => self.enter_lobby(),
+            _ => {}
+        }
+    }
+    /// Starts the match's next game, or another game or match against the
+    /// same opponent once it is over.
+    fn rematch(&mut self, rect: &Rect) {
+        match self.series {
+            Some(series) if !series.over() => {}
+            _ => self.start_match(),
+        }
+        self.go(Event::Rematch);
+        self.board = self.new_board(rect);
+        self.clock = None;
+    }
+    /// Sets up a match of `best_of` games, or none for single games.
+    fn start_match(&mut self) {
+        self.series = match self.best_of {
+            1 => None,
+            best_of => Some(Series::new(best_of)),
+        };
+    }
+    /// Shows the analysed game `steps` moves on or back.
+    fn step(&mut self, steps: isize) {
+        if let Some(analysis) = &mut self.analysis {
+            analysis.step_by(steps);
+        }
+    }
+    /// Starts a game against the computer, on the side picked.
+    fn choose_side(&mut self, rect: &Rect, player_mode: PlayerMode) {
+        self.player_mode = player_mode;
+        self.go(Event::ChooseSide);
+        self.start_match();
+        self.board = self.new_board(rect);
+        self.clock = None;
+    }
+    /// Makes a profile with the typed name and plays as it.
+    pub fn create_profile(&mut self) {
+        let name = self.typed_name.trim().to_string();
+        if !name.is_empty() {
+            self.pick_profile(name);
+        }
+    }
+    /// Types a letter of a new profile's name, or takes one back.
+    pub fn type_name(&mut self, key: Key, shift: bool) {
+        if key == self.config.keys.erase.0 {
+            self.typed_name.pop();
+            return;
+        }
+        // keys are named after what they type, like `A` and `Key1`
+        let name = format!("{:?}", key);
+        let mut chars = name.trim_start_matches("Key").chars();
+        let letter = match (chars.next(), chars.next()) {
+            (Some(letter), None) if letter.is_ascii_alphanumeric() && shift => letter,
+            (Some(letter), None) if letter.is_ascii_alphanumeric() => {
+                letter.to_ascii_lowercase()
+            }
+            _ if key == Key::Space => ' ',
+            _ => return,
+        };
+        if self.typed_name.len() < NAME_LENGTH {
+            self.typed_name.push(letter);
+        }
+    }
+    /// Saves the leaderboard where spreadsheets can read it.
+    fn export_ratings(&mut self) {
+        let exported = std::fs::File::create(ratings::EXPORT_PATH)
+            .and_then(|file| self.ratings.export_csv(file));
+        self.status = match exported {
+            Ok(()) => format!("Saved to {}", ratings::EXPORT_PATH),
+            Err(error) => format!("Could not export: {}", error),
+        };
+    }
+    /// Puts on the theme called `name`, if there is one.
+    pub fn use_theme(&mut self, name: &str) -> bool {
+        match self.themes.iter().find(|theme| theme.name == name) {
+            Some(theme) => {
+                self.theme = theme.clone().with_colors(&self.config.colors);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Moves on to the theme after the one in use.
+    fn next_theme(&mut self) {
+        let current = self
+            .themes
+            .iter()
+            .position(|theme| theme.name == self.theme.name)
+            .unwrap_or(0);
+        let next = self.themes[(current + 1) % self.themes.len()].name.clone();
+        self.use_theme(&next);
+    }
+    fn settings_button(rect: &Rect) -> Rect {
+        let s = scale(rect);
+        Rect::from_x_y_w_h(0.0, rect.top() - 30.0 * s, 160.0 * s, 40.0 * s)
+    }
+    /// Runs the clocks of a game played in this window, starting fresh ones
+    /// whenever a new game starts.
+    pub fn tick_clock(&mut self, elapsed: Duration) {
+        let opponent = self.screen.opponent();
+        let timed =
+            opponent == Some(Opponent::Computer) || opponent == Some(Opponent::HotSeat);
+        if !timed {
+            self.clock = None;
+            return;
+        }
+        let stale = match &self.clock {
+            Some(clock) => !clock.follows(&self.board),
+            None => true,
+        };
+        if stale {
+            self.clock = Clock::new(self.time_control);
+        }
+        let elapsed = elapsed.checked_sub(self.thought).unwrap_or_default();
+        self.thought = Duration::from_secs(0);
+        if let Some(clock) = &mut self.clock {
+            clock.tick(&mut self.board, elapsed);
+        }
+    }
+    /// Runs the board's animations on by `elapsed`.
+    pub fn animate(&mut self, elapsed: Duration) {
+        self.animation.update(&self.board, elapsed);
+    }
+    /// Writes out what has happened in the game being played, for those
+    /// who asked for announcements.
+    pub fn announce(&mut self) {
+        let playing = self.screen.opponent().is_some();
+        if let Some(announcer) = &mut self.announcer {
+            if playing {
+                announcer.announce(&self.board);
+            } else {
+                announcer.forget();
+            }
+        }
+    }
+    /// Lets the computer answer in a single player game.
+    pub fn computer_turn(&mut self) {
+        let against_bot = self.screen == Screen::Playing(Opponent::Computer);
+        if against_bot && self.board.player_2 == self.board.current_player {
+            let started = Instant::now();
+            self.board.computer_move(self.difficulty);
+            if let Some(clock) = &mut self.clock {
+                self.thought = started.elapsed();
+                clock.tick(&mut self.board, self.thought);
+            }
+        }
+    }
+    fn show_clock(&self, draw: &app::Draw, rect: &Rect) {
+        let clock = match &self.clock {
+            Some(clock) => clock,
+            None => return,
+        };
+        let s = scale(rect);
+        for &(side, x) in &[
+            (Player::Player1, rect.left() + 60.0 * s),
+            (Player::Player2, rect.right() - 60.0 * s),
+        ] {
+            let area = Rect::from_x_y_w_h(x, rect.top() - 20.0 * s, 110.0 * s, 34.0 * s);
+            let running = self.board.state == BoardState::InGame
+                && self.board.current_player == side;
+            let color = if running {
+                self.theme.field
+            } else {
+                self.theme.button.fill
+            };
+            draw.rect().xy(area.xy()).wh(area.wh()).color(color.0);
+            let label = format!(
+                "{} {}",
+                side.get_sigil().to_string(),
+                clock::show(clock.remaining[side.index()])
+            );
+            let ct = text(&label)
+                .font_size(self.font(self.config.fonts.button))
+                .build(area);
+            draw.path()
+                .fill()
+                .color(self.theme.button.label.0)
+                .events(ct.path_events());
+        }
+    }
+    /// Starts listening for a guest at `address`.
+    fn host(&mut self) {
+        match Door::open(&self.address) {
+            Ok(door) => {
+                self.door = Some(door);
+                self.go(Event::Host);
+            }
+            Err(error) => eprintln!("could not host on {}: {}", self.address, error),
+        }
+    }
+    /// Starts joining the game hosted at `address`, playing O. The game
+    /// starts from `poll_network` once the host has answered.
+    fn join(&mut self) {
+        self.connecting = Some(Peer::join(self.address.clone()));
+    }
+    /// Starts watching the game hosted at `address`, the same way.
+    fn watch(&mut self) {
+        self.connecting = Some(Peer::watch(self.address.clone()));
+    }
+    fn go_online(
+        &mut self,
+        rect: &Rect,
+        mut peer: Peer,
+        variant: Variant,
+        setup: Setup,
+    ) {
+        self.variant = variant;
+        self.setup = setup;
+        self.board = self.new_board(rect);
+        peer.introduce(&self.player);
+        self.peer = Some(peer);
+        self.go(Event::Connect);
+    }
+    /// Picks up what the computer has found out about the game being gone
+    /// over.
+    pub fn poll_analysis(&mut self) {
+        if let Some(analysis) = &mut self.analysis {
+            analysis.update();
+        }
+    }
+    /// Picks up newcomers while hosting, and the other side's messages while
+    /// online.
+    pub fn poll_network(&mut self, rect: &Rect) {
+        if let Some(joined) = self.connecting.as_ref().and_then(Connecting::poll) {
+            self.connecting = None;
+            match joined {
+                Ok((peer, variant, setup)) => {
+                    self.player_mode = match peer.role {
+                        Role::Spectator => PlayerMode::PlayUndecided,
+                        _ => PlayerMode::PlayO,
+                    };
+                    self.go_online(rect, peer, variant, setup);
+                }
+                Err(error) => eprintln!("could not reach {}: {}", self.address, error),
+            }
+        }
+        if let Some(door) = &self.door {
+            match door.poll() {
+                Ok(visitors) => {
+                    for visitor in visitors {
+                        self.welcome(rect, visitor);
+                    }
+                }
+                Err(error) => {
+                    eprintln!("stopped hosting: {}", error);
+                    self.door = None;
+                    if self.screen == Screen::Hosting {
+                        self.go(Event::Disconnect);
+                    }
+                }
+            }
+        }
+        if self.screen.opponent() != Some(Opponent::Online) {
+            return;
+        }
+        if let Some(peer) = &mut self.peer {
+            peer.update(&mut self.board);
+            // a finished game stays up until it is left from the game over panel
+            if peer.closed && self.board.state == BoardState::InGame {
+                eprintln!("the other side left");
+                self.leave_network();
+                self.go(Event::Disconnect);
+                self.board = self.new_board(rect);
+            }
+        }
+    }
+    /// The first player to turn up starts the game; anyone after that may
+    /// only watch it.
+    fn welcome(&mut self, rect: &Rect, visitor: io::Result<Visitor>) {
+        let welcomed = visitor.and_then(|visitor| match &mut self.peer {
+            Some(peer) => peer.admit(visitor),
+            None => {
+                let peer = Peer::host(visitor, self.variant, self.setup)?;
+                self.player_mode = PlayerMode::PlayX;
+                self.go_online(rect, peer, self.variant, self.setup);
+                Ok(())
+            }
+        });
+        if let Err(error) = welcomed {
+            eprintln!("turned a visitor away: {}", error);
+        }
+    }
+    /// Clicks while online make this side's move.
+    pub fn check_online(&mut self, cell: Option<usize>) {
+        let peer = match &mut self.peer {
+            Some(peer) => peer,
+            None => return,
+        };
+        if peer.role == Role::Spectator || self.board.current_player != peer.side {
+            return;
+        }
+        let board = &mut self.board;
+        if let Some(play) = cell.and_then(|cell| board.move_to(cell)) {
+            if let Err(error) = peer.play_local(&mut self.board, play) {
+                eprintln!("move refused: {}", error);
+            }
+        }
+    }
+    /// Stops hosting, and leaves the game with the other copy of the app.
+    fn leave_network(&mut self) {
+        if let Some(peer) = &mut self.peer {
+            peer.leave();
+        }
+        self.peer = None;
+        self.connecting = None;
+        self.door = None;
+    }
+    fn lobby_button(rect: &Rect, row: usize, column: usize) -> Rect {
+        let s = scale(rect);
+        let size = vec2(150.0 * s, 60.0 * s);
+        let y = rect.top() / 2.0 - row as f32 * (size.y + 10.0 * s);
+        widget::row(pt2(0.0, y), size, rect.w() / 3.0 - size.x, 3)[column]
+    }
+    fn lobby_label(&self, button: LobbyButton) -> String {
+        match button {
+            LobbyButton::HotSeat => "Hot Seat".to_string(),
+            LobbyButton::QuickMatch => "Quick Match".to_string(),
+            LobbyButton::PlayBot => "Play Bot".to_string(),
+            LobbyButton::NewPublic => "New Public".to_string(),
+            LobbyButton::NewPrivate => "New Private".to_string(),
+            LobbyButton::Difficulty => format!("Bot: {}", self.difficulty.to_string()),
+            LobbyButton::Refresh => "Refresh".to_string(),
+            LobbyButton::JoinCode => format!("Join: {:_<6}", self.code),
+            LobbyButton::Back => "Back".to_string(),
+        }
+    }
+    /// The row of the open games list under the lobby's buttons.
+    fn listing_row(rect: &Rect, index: usize) -> Rect {
+        let top = Model::lobby_button(rect, LOBBY_BUTTONS.len(), 0).top();
+        let s = scale(rect);
+        let height = 30.0 * s;
+        let y = top - height / 2.0 - (index + 1) as f32 * height;
+        Rect::from_x_y_w_h(0.0, y, rect.w() / 3.0 * 2.0 + 150.0 * s, height - 4.0 * s)
+    }
+    /// The rows of the open games list that fit in the window.
+    fn visible_listings(&self, rect: &Rect) -> usize {
+        (0..self.open_games.len())
+            .take_while(|&i| Model::listing_row(rect, i).bottom() > rect.bottom())
+            .count()
+    }
+    /// Connects to the server on the way into the lobby and asks what is
+    /// open.
+    pub fn enter_lobby(&mut self) {
+        self.seat = None;
+        self.status = format!("New games use the {} board", self.variant.to_string());
+        self.request(Request::List);
+    }
+    /// Sends `request` to the server, connecting first if need be.
+    fn request(&mut self, request: Request) {
+        if self.lobby.iter().all(|lobby| lobby.closed) {
+            self.lobby = Some(Lobby::connect(&self.server));
+        }
+        if let Some(lobby) = &mut self.lobby {
+            lobby.send(request);
+        }
+    }
+    fn create(&mut self, bot: bool, private: bool) {
+        self.request(Request::Create {
+            variant: self.variant,
+            setup: self.setup,
+            side: None,
+            bot,
+            difficulty: self.difficulty,
+            private,
+            name: self.name(),
+        });
+    }
+    fn press_lobby(&mut self, rect: &Rect, button: LobbyButton) {
+        match button {
+            LobbyButton::HotSeat => {
+                self.go(Event::HotSeat);
+                self.start_match();
+                self.board = self.new_board(rect);
+            }
+            LobbyButton::QuickMatch => self.request(Request::QuickMatch {
+                variant: self.variant,
+                name: self.name(),
+            }),
+            LobbyButton::PlayBot => self.create(true, false),
+            LobbyButton::NewPublic => self.create(false, false),
+            LobbyButton::NewPrivate => self.create(false, true),
+            LobbyButton::Difficulty => self.difficulty = self.difficulty.next(),
+            LobbyButton::Refresh => self.request(Request::List),
+            LobbyButton::JoinCode if self.code.len() == CODE_LENGTH => {
+                let code = self.code.clone();
+                self.request(Request::Join {
+                    game: None,
+                    code: Some(code),
+                    name: self.name(),
+                });
+            }
+            LobbyButton::JoinCode => {
+                self.status = "Type the six digit code first".to_string();
+            }
+            LobbyButton::Back => self.back(rect),
+        }
+    }
+    /// Types a digit of a join code, or takes one back.
+    pub fn type_code(&mut self, key: Key) {
+        let digit = match digit(key) {
+            Some(digit) => digit,
+            None if key == self.config.keys.erase.0 => {
+                self.code.pop();
+                return;
+            }
+            None => return,
+        };
+        if self.code.len() < CODE_LENGTH {
+            self.code.push(std::char::from_digit(digit, 10).unwrap());
+        }
+    }
+    /// Takes in the server's replies while in the lobby or a server game.
+    pub fn poll_lobby(&mut self, rect: &Rect) {
+        let replies: Vec<Reply> = match &mut self.lobby {
+            Some(lobby) => std::iter::from_fn(|| lobby.poll()).collect(),
+            None => return,
+        };
+        for reply in replies {
+            self.take_reply(rect, reply);
+        }
+        if self.lobby.iter().any(|lobby| lobby.closed) {
+            let failure = self.lobby.take().and_then(|lobby| lobby.failure);
+            self.status = match failure {
+                Some(error) => format!("Could not reach {}: {}", self.server, error),
+                None => format!("Lost the connection to {}", self.server),
+            };
+            let playing = self.screen == Screen::Playing(Opponent::Server);
+            if playing && self.board.state == BoardState::InGame {
+                self.go(Event::Disconnect);
+                self.seat = None;
+            }
+        }
+    }
+    fn take_reply(&mut self, rect: &Rect, reply: Reply) {
+        match reply {
+            Reply::Seated { game, side, code } => {
+                self.seat = Some((game, side));
+                self.rival = None;
+                self.player_mode = match side {
+                    Player::Player1 => PlayerMode::PlayX,
+                    Player::Player2 => PlayerMode::PlayO,
+                };
+                self.board = self.new_board(rect);
+                self.go(Event::Seated);
+                self.status = match code {
+                    Some(code) => format!("Share the code {} with a friend", code),
+                    None => format!(
+                        "Game {}, you play {}",
+                        game,
+                        side.get_sigil().to_string()
+                    ),
+                };
+                self.code.clear();
+            }
+            Reply::Waiting => self.status = "Looking for an opponent...".to_string(),
+            Reply::Games { games } => {
+                if games.is_empty() {
+                    self.status = "No open games right now".to_string();
+                }
+                self.open_games = games;
+            }
+            Reply::State {
+                game,
+                players,
+                state,
+            } => {
+                if let Some((_, side)) = self.seat.filter(|&(id, _)| id == game) {
+                    lobby::mirror(&mut self.board, &state);
+                    self.rival = players[(-side).index()].clone();
+                }
+            }
+            Reply::Left { game, side } => {
+                if self.seat == Some((game, -side)) {
+                    let sigil = side.get_sigil().to_string();
+                    self.status = format!("{} left, their seat is open again", sigil);
+                }
+            }
+            Reply::Error { message } => self.status = message,
+        }
+    }
+    /// Clicks in a server game make this side's move.
+    pub fn check_matched(&mut self, cell: Option<usize>) {
+        let (game, side) = match self.seat {
+            Some(seat) => seat,
+            None => return,
         };
-        GameMode::Waiting
+        if self.board.current_player != side {
+            return;
+        }
+        if let Some(play) = cell.and_then(|cell| self.board.move_to(cell)) {
+            self.request(Request::Move { game, play });
+        }
+    }
+    /// A fresh board with the chosen variant and starting position, with
+    /// the side whose turn it is in a match moving first.
+    pub fn new_board(&self, rect: &Rect) -> Board {
+        let first = self.series.map_or(Player::Player1, |series| series.first());
+        Board::new(
+            Model::board_rect(rect),
+            self.player_mode,
+            self.variant.rules(),
+        )
+        .with_setup(self.setup)
+        .with_first(first)
+    }
+    /// The largest square that fits in the window, in its middle.
+    pub fn board_rect(rect: &Rect) -> Rect {
+        let side = rect.w().min(rect.h());
+        Rect::from_x_y_w_h(rect.x(), rect.y(), side, side)
+    }
+    /// A screen's title, across the top.
+    fn title_bar(rect: &Rect) -> Rect {
+        let s = scale(rect);
+        Rect::from_x_y_w_h(0.0, rect.top() - 30.0 * s, rect.w(), 40.0 * s)
+    }
+    /// A line of news across the top of a game.
+    fn status_bar(rect: &Rect) -> Rect {
+        let s = scale(rect);
+        Rect::from_x_y_w_h(0.0, rect.top() - 15.0 * s, rect.w(), 30.0 * s)
+    }
+    /// The scoreboard along the bottom of a game.
+    fn score_bar(rect: &Rect) -> Rect {
+        let s = scale(rect);
+        Rect::from_x_y_w_h(0.0, rect.bottom() + 15.0 * s, rect.w(), 30.0 * s)
+    }
+    /// A font size from the settings, grown or shrunk with the window.
+    fn font(&self, size: u32) -> u32 {
+        ((size as f32 * self.scale).round() as u32).max(8)
+    }
+    /// Moves on to the game over screen once the game on the board ends,
+    /// counting the game in the session's score unless it was only watched.
+    pub fn end_game(&mut self) {
+        if self.board.state == BoardState::InGame {
+            return;
+        }
+        if let Screen::Playing(opponent) = self.screen {
+            let watched = opponent == Opponent::Online
+                && self.peer.iter().any(|peer| peer.role == Role::Spectator);
+            if !watched {
+                self.score.record(&self.board.state);
+            }
+            if let Some(series) = &mut self.series {
+                series.record(&self.board.state);
+            }
+        }
+        self.go(Event::Finish);
+    }
+    /// How the game on the board ended, as the game over panel says it.
+    fn result(&self) -> String {
+        let (winner, on_time) = match &self.board.state {
+            BoardState::Winner(winner, line) => (*winner, line.is_empty()),
+            _ => return "A draw".to_string(),
+        };
+        let result = match self.screen.opponent() {
+            Some(Opponent::Computer) if winner == self.board.player_1 => {
+                "You win".to_string()
+            }
+            Some(Opponent::Computer) => "The computer wins".to_string(),
+            _ => format!("{} wins", winner.get_sigil().to_string()),
+        };
+        if on_time {
+            format!("{} on time", result)
+        } else {
+            result
+        }
+    }
+    /// The match so far, if one is being played, and the session's score.
+    fn scoreboard(&self) -> Vec<String> {
+        let session = format!("Session: {}", self.score);
+        let series = self.series.map(|series| series.summary());
+        series.into_iter().chain(Some(session)).collect()
+    }
+    /// Draws the panel's box, and `lines` in it from the top, the first in
+    /// the title font.
+    fn show_panel(&self, draw: &app::Draw, rect: &Rect, lines: &[String]) {
+        let s = scale(rect);
+        let panel = Model::panel(rect);
+        let background = self.theme.background.0;
+        draw.rect().xy(panel.xy()).wh(panel.wh()).color(srgba(
+            background.red,
+            background.green,
+            background.blue,
+            220,
+        ));
+        widget::outline(draw, panel, 0.0, 2.0 * s, self.theme.button.border.0);
+        let mut top = panel.top() - 8.0 * s;
+        for (index, line) in lines.iter().enumerate() {
+            let (height, size) = match index {
+                0 => (36.0 * s, self.config.fonts.title),
+                _ => (26.0 * s, self.config.fonts.status),
+            };
+            let area = Rect::from_x_y_w_h(0.0, top - height / 2.0, panel.w(), height);
+            self.show_text(draw, area, line, size);
+            top -= height;
+        }
+    }
+    /// Fits the board and text to the window. Only how the game is drawn
+    /// changes, never the game itself.
+    pub fn layout(&mut self, rect: &Rect) {
+        self.board.rect = Model::board_rect(rect);
+        self.scale = scale(rect);
+    }
+    /// The board, fading in while it is new.
+    fn show_board(&self, draw: &app::Draw, rect: &Rect) {
+        let rect = &Model::board_rect(rect);
+        self.board
+            .display(draw, rect, &self.theme.board, &self.animation);
+        let fade = self.animation.fade();
+        if fade < 1.0 {
+            let background = self.theme.background.0;
+            let alpha = ((1.0 - fade) * 255.0) as u8;
+            draw.rect().xy(rect.xy()).wh(rect.wh()).color(srgba(
+                background.red,
+                background.green,
+                background.blue,
+                alpha,
+            ));
+        }
+    }
+    /// Writing straight on the background.
+    fn show_text(&self, draw: &app::Draw, area: Rect, label: &str, size: u32) {
+        let lt = text(label).font_size(self.font(size)).build(area);
+        draw.path()
+            .fill()
+            .color(self.theme.text.0)
+            .events(lt.path_events());
+    }
+    /// A line of a list, written from the left.
+    fn show_line(&self, draw: &app::Draw, area: Rect, label: &str) {
+        let lt = text(label)
+            .font_size(self.font(self.config.fonts.status))
+            .left_justify()
+            .build(area);
+        draw.path()
+            .fill()
+            .color(self.theme.text.0)
+            .events(lt.path_events());
     }
     pub fn display(&self, draw: &app::Draw, rect: &Rect) {
-        match &self.mode {
-            GameMode::Waiting => {
-                let x_single_player = rect.left() / 3.0;
-                let y_single_player = 0.0;
-                let x_multi_player = rect.right() / 3.0;
-                let y_multi_player = 0.0;
-                let width = 150.0;
-                let height = width / 1.618;
-
-                draw.rect()
-                    .x_y(x_single_player, y_single_player)
-                    .w_h(width, height)
-                    .color(DARKGREY);
-                draw.rect()
-                    .x_y(x_multi_player, y_multi_player)
-                    .w_h(width, height)
-                    .color(DARKGREY);
-                let single_player =
-                    Rect::from_x_y_w_h(x_single_player, y_single_player, width, height);
-                let multi_player =
-                    Rect::from_x_y_w_h(x_multi_player, y_multi_player, width, height);
-
-                let spt = text("Single Player").font_size(20).build(single_player);
-                draw.path().fill().color(BLACK).events(spt.path_events());
-                let mpt = text("Mutliplayer").font_size(20).build(multi_player);
-                draw.path().fill().color(BLACK).events(mpt.path_events());
-            }
-            GameMode::SinglePlayer(x) => match x {
-                PlayerMode::PlayUndecided => {
-                    let x_single_player = rect.left() / 3.0;
-                    let y_single_player = 0.0;
-                    let x_multi_player = rect.right() / 3.0;
-                    let y_multi_player = 0.0;
-                    let width = 150.0;
-                    let height = width / 1.618;
-
-                    draw.rect()
-                        .x_y(x_single_player, y_single_player)
-                        .w_h(width, height)
-                        .color(DARKGREY);
-                    draw.rect()
-                        .x_y(x_multi_player, y_multi_player)
-                        .w_h(width, height)
-                        .color(DARKGREY);
-                    let single_player = Rect::from_x_y_w_h(
-                        x_single_player,
-                        y_single_player,
-                        width,
-                        height,
-                    );
-                    let multi_player = Rect::from_x_y_w_h(
-                        x_multi_player,
-                        y_multi_player,
-                        width,
-                        height,
+        let s = scale(rect);
+        match self.screen {
+            Screen::Hosting => {
+                let label = format!(
+                    "Waiting for a player on {}\n\nClick to cancel",
+                    self.address
+                );
+                self.show_text(draw, rect.pad(20.0), &label, self.config.fonts.title);
+            }
+            Screen::MainMenu | Screen::SideSelect => {}
+            Screen::Settings => {
+                let title = Model::title_bar(rect);
+                self.show_text(draw, title, "Settings", self.config.fonts.title);
+            }
+            Screen::Playing(Opponent::Online) | Screen::GameOver(Opponent::Online) => {
+                self.show_board(draw, rect);
+                if let Some(peer) = &self.peer {
+                    let label = match peer.role {
+                        Role::Spectator => {
+                            format!("Watching, spectators: {}", peer.spectators)
+                        }
+                        _ => format!("Spectators: {}", peer.spectators),
+                    };
+                    let location = Model::status_bar(rect);
+                    self.show_text(draw, location, &label, self.config.fonts.status);
+                }
+            }
+            Screen::Lobby => {
+                let location = Model::title_bar(rect);
+                self.show_text(draw, location, &self.status, self.config.fonts.status);
+            }
+            Screen::Ratings => {
+                let title = Model::title_bar(rect);
+                let label = format!("Ratings for {}", self.player);
+                self.show_text(draw, title, &label, self.config.fonts.title);
+                let height = 26.0 * scale(rect);
+                let bottom = Model::footer_buttons(rect)[1].top();
+                let rows = self.ratings.leaderboard();
+                for (index, (name, rating)) in rows.iter().enumerate() {
+                    let y = title.bottom() - height / 2.0 - index as f32 * height;
+                    if y - height / 2.0 < bottom {
+                        break;
+                    }
+                    let row = Rect::from_x_y_w_h(0.0, y, rect.w() - 40.0 * s, height);
+                    let label = format!(
+                        "{}. {}   {:.0}   ({} games)",
+                        index + 1,
+                        name,
+                        rating.rating,
+                        rating.games
                     );
-
-                    let spt = text("Play First?").font_size(20).build(single_player);
-                    draw.path().fill().color(BLACK).events(spt.path_events());
-                    let mpt = text("Play Second?").font_size(20).build(multi_player);
-                    draw.path().fill().color(BLACK).events(mpt.path_events());
+                    self.show_line(draw, row, &label);
                 }
-                _ => {
-                    // right vertical line
-                    draw.line()
-                        .start(pt2(rect.right() / 3.0, rect.top()))
-                        .end(pt2(rect.right() / 3.0, rect.bottom()))
-                        .stroke_weight(2.0)
-                        .color(DARKGREY);
-                    // left vertical line
-                    draw.line()
-                        .start(pt2(rect.left() / 3.0, rect.top()))
-                        .end(pt2(rect.left() / 3.0, rect.bottom()))
-                        .stroke_weight(2.0)
-                        .color(DARKGREY);
-                    // bottom horizontal line
-                    draw.line()
-                        .start(pt2(rect.left(), rect.bottom() / 3.0))
-                        .end(pt2(rect.right(), rect.bottom() / 3.0))
-                        .stroke_weight(2.0)
-                        .color(DARKGREY);
-                    // top horizontal line
-                    draw.line()
-                        .start(pt2(rect.left(), rect.top() / 3.0))
-                        .end(pt2(rect.right(), rect.top() / 3.0))
-                        .stroke_weight(2.0)
-                        .color(DARKGREY);
-                    self.board.display(draw, &rect);
-                }
-            },
-            _ => {
-                // right vertical line
-                draw.line()
-                    .start(pt2(rect.right() / 3.0, rect.top()))
-                    .end(pt2(rect.right() / 3.0, rect.bottom()))
-                    .stroke_weight(2.0)
-                    .color(DARKGREY);
-                // left vertical line
-                draw.line()
-                    .start(pt2(rect.left() / 3.0, rect.top()))
-                    .end(pt2(rect.left() / 3.0, rect.bottom()))
-                    .stroke_weight(2.0)
-                    .color(DARKGREY);
-                // bottom horizontal line
-                draw.line()
-                    .start(pt2(rect.left(), rect.bottom() / 3.0))
-                    .end(pt2(rect.right(), rect.bottom() / 3.0))
-                    .stroke_weight(2.0)
-                    .color(DARKGREY);
-                // top horizontal line
-                draw.line()
-                    .start(pt2(rect.left(), rect.top() / 3.0))
-                    .end(pt2(rect.right(), rect.top() / 3.0))
-                    .stroke_weight(2.0)
-                    .color(DARKGREY);
-                self.board.display(draw, &rect);
+                if rows.is_empty() {
+                    let label = "No rated games yet";
+                    self.show_text(draw, *rect, label, self.config.fonts.status);
+                }
+                let status = Model::footer_buttons(rect)[1].shift_y(45.0 * s);
+                let status = Rect::from_x_y_w_h(0.0, status.y(), rect.w(), 30.0 * s);
+                self.show_text(draw, status, &self.status, self.config.fonts.status);
+            }
+            Screen::Profiles => {
+                let title = Model::title_bar(rect);
+                let label = "Who is playing?";
+                self.show_text(draw, title, label, self.config.fonts.title);
+                let field = Model::name_field(rect);
+                draw.rect()
+                    .xy(field.xy())
+                    .wh(field.wh())
+                    .color(self.theme.field.0);
+                let label = format!("New: {}_", self.typed_name);
+                self.show_text(draw, field, &label, self.config.fonts.button);
+            }
+            Screen::Stats => {
+                let title = Model::title_bar(rect);
+                self.show_text(draw, title, &self.player, self.config.fonts.title);
+                let stats = self
+                    .profiles
+                    .players
+                    .get(&self.player)
+                    .cloned()
+                    .unwrap_or_default();
+                for (index, line) in stats.lines().iter().enumerate() {
+                    let y = title.bottom() - (15.0 + index as f32 * 30.0) * s;
+                    let row = Rect::from_x_y_w_h(0.0, y, rect.w() - 40.0 * s, 30.0 * s);
+                    self.show_line(draw, row, line);
+                }
+            }
+            Screen::Playing(Opponent::Server) | Screen::GameOver(Opponent::Server) => {
+                self.show_board(draw, rect);
+                let location = Model::status_bar(rect);
+                self.show_text(draw, location, &self.status, self.config.fonts.status);
             }
+            Screen::Playing(_) | Screen::GameOver(_) => {
+                self.show_board(draw, rect);
+                self.show_clock(draw, rect);
+            }
+            Screen::Analysis(_) => {
+                if let Some(analysis) = &self.analysis {
+                    let area = Model::board_rect(rect);
+                    let animation = Animation::default();
+                    let board = analysis.board();
+                    board.display(draw, &area, &self.theme.board, &animation);
+                    self.show_panel(draw, rect, &analysis.describe());
+                }
+            }
+        }
+        match self.screen {
+            Screen::Playing(_) => {
+                let location = Model::score_bar(rect);
+                let label = self.scoreboard().join("      ");
+                self.show_text(draw, location, &label, self.config.fonts.status);
+            }
+            Screen::GameOver(_) => {
+                let mut lines = vec![self.result()];
+                lines.extend(self.scoreboard());
+                self.show_panel(draw, rect, &lines);
+            }
+            _ => {}
         }
+        self.show_buttons(draw, rect);
+        self.show_focus(draw, rect);
+    }
+}
+
+/// The side of the square window the layout's sizes are given for.
+const DESIGN_SIZE: f32 = 600.0;
+
+/// How much bigger than `DESIGN_SIZE` the window's shorter side is. Sizes in
+/// the layout are multiplied by this, so everything keeps its proportions.
+fn scale(rect: &Rect) -> f32 {
+    (rect.w().min(rect.h()) / DESIGN_SIZE).max(0.5)
+}
+
+/// The size of the big buttons in the middle of the main menu: 150 wide
+/// at the design size, and a golden rectangle.
+fn big_button(rect: &Rect) -> Vector2 {
+    let width = 150.0 * scale(rect);
+    vec2(width, width / 1.618)
+}
+
+/// The digit a number key types, on the main keys or the numeric keypad.
+fn digit(key: Key) -> Option<u32> {
+    match key {
+        Key::Key0 | Key::Numpad0 => Some(0),
+        Key::Key1 | Key::Numpad1 => Some(1),
+        Key::Key2 | Key::Numpad2 => Some(2),
+        Key::Key3 | Key::Numpad3 => Some(3),
+        Key::Key4 | Key::Numpad4 => Some(4),
+        Key::Key5 | Key::Numpad5 => Some(5),
+        Key::Key6 | Key::Numpad6 => Some(6),
+        Key::Key7 | Key::Numpad7 => Some(7),
+        Key::Key8 | Key::Numpad8 => Some(8),
+        Key::Key9 | Key::Numpad9 => Some(9),
+        _ => None,
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn the_board_is_the_largest_centered_square() {
+        let wide = Rect::from_w_h(1000.0, 600.0);
+        let board = Model::board_rect(&wide);
+        assert_eq!((board.w(), board.h()), (600.0, 600.0));
+        assert_eq!((board.x(), board.y()), (0.0, 0.0));
+        assert_eq!(scale(&wide), 1.0);
+        assert_eq!(scale(&Rect::from_w_h(1200.0, 1500.0)), 2.0);
     }
 }
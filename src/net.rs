@@ -0,0 +1,783 @@
+//! Two-player games between two copies of the app, over TCP.
+//!
+//! The host owns the game. It checks every move the guest sends against its
+//! own board and only plays the ones that pass; the guest waits to hear back
+//! before showing its own moves. Messages are single lines of text, and both
+//! sides open with `HELLO <version>` so mismatched builds refuse each other.
+//!
+//! Anyone else can look on: a spectator opens with `WATCH <version>` instead,
+//! gets the board and every move so far, and then each new move. The host
+//! keeps spectators apart from its opponent and never takes moves from them.
+//!
+//! Once connected, the two players each send `NAME <profile>` so the game can
+//! be rated under both names.
+use crate::board::{Board, MoveError};
+use crate::player::Player;
+use crate::rules::{Move, Variant, VARIANTS};
+use crate::setup::{Setup, PRESETS};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// Bumped whenever a message changes meaning.
+pub const VERSION: u32 = 3;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+
+/// How long either side waits for the other during the handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+const REJECTIONS: [(MoveError, &str); 4] = [
+    (MoveError::GameOver, "game-over"),
+    (MoveError::OutOfTurn, "out-of-turn"),
+    (MoveError::Occupied, "occupied"),
+    (MoveError::Illegal, "illegal"),
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Message {
+    Hello(u32),
+    /// Sent instead of `Hello` by spectators.
+    Watch(u32),
+    /// The host's board, sent once the versions match.
+    Start(Variant, Setup),
+    Move(Move),
+    /// The host played the guest's last move.
+    Accepted,
+    Rejected(MoveError),
+    /// How many spectators the host has.
+    Watchers(usize),
+    /// The sender's profile name.
+    Name(String),
+    Bye,
+}
+
+impl Message {
+    pub fn parse(line: &str) -> Option<Message> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |i: usize| words.get(i).and_then(|word| word.parse::<u64>().ok());
+        let message = match words.as_slice() {
+            ["HELLO", _] => Message::Hello(number(1)? as u32),
+            ["WATCH", _] => Message::Watch(number(1)? as u32),
+            ["START", _, "EMPTY"] => Message::Start(variant(number(1)?)?, Setup::Empty),
+            ["START", _, "PRESET", _] => {
+                let preset = number(3)? as usize;
+                if preset >= PRESETS.len() {
+                    return None;
+                }
+                Message::Start(variant(number(1)?)?, Setup::Preset(preset))
+            }
+            ["START", _, "RANDOM", _] => {
+                Message::Start(variant(number(1)?)?, Setup::Random(number(3)?))
+            }
+            ["MOVE", "PLACE", _] => Message::Move(Move::Place(number(2)? as usize)),
+            ["MOVE", "SLIDE", _, _] => {
+                Message::Move(Move::Slide(number(2)? as usize, number(3)? as usize))
+            }
+            ["ACCEPTED"] => Message::Accepted,
+            ["REJECTED", code] => Message::Rejected(
+                REJECTIONS
+                    .iter()
+                    .find(|(_, name)| name == code)
+                    .map(|(error, _)| *error)?,
+            ),
+            ["WATCHERS", _] => Message::Watchers(number(1)? as usize),
+            ["NAME", name @ ..] if !name.is_empty() => Message::Name(name.join(" ")),
+            ["BYE"] => Message::Bye,
+            _ => return None,
+        };
+        Some(message)
+    }
+}
+
+fn variant(index: u64) -> Option<Variant> {
+    VARIANTS.get(index as usize).copied()
+}
+
+impl std::fmt::Display for Message {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Message::Hello(version) => write!(f, "HELLO {}", version),
+            Message::Watch(version) => write!(f, "WATCH {}", version),
+            Message::Start(variant, setup) => {
+                let index = VARIANTS.iter().position(|v| v == variant).unwrap();
+                match setup {
+                    Setup::Empty => write!(f, "START {} EMPTY", index),
+                    Setup::Preset(i) => write!(f, "START {} PRESET {}", index, i),
+                    Setup::Random(seed) => write!(f, "START {} RANDOM {}", index, seed),
+                }
+            }
+            Message::Move(Move::Place(to)) => write!(f, "MOVE PLACE {}", to),
+            Message::Move(Move::Slide(from, to)) => {
+                write!(f, "MOVE SLIDE {} {}", from, to)
+            }
+            Message::Accepted => write!(f, "ACCEPTED"),
+            Message::Rejected(error) => {
+                let (_, code) = REJECTIONS.iter().find(|(e, _)| e == error).unwrap();
+                write!(f, "REJECTED {}", code)
+            }
+            Message::Watchers(count) => write!(f, "WATCHERS {}", count),
+            Message::Name(name) => write!(f, "NAME {}", name),
+            Message::Bye => write!(f, "BYE"),
+        }
+    }
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+fn read_message(reader: &mut impl BufRead) -> io::Result<Message> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "connection closed",
+        ));
+    }
+    Message::parse(&line).ok_or_else(|| invalid("unreadable message"))
+}
+
+/// Messages from the host's opponent come from source 0; spectators are
+/// numbered from 1.
+const OPPONENT: usize = 0;
+
+/// Hands the reading half of a connection to a thread that passes messages
+/// on, tagged with `source`, until the connection ends.
+fn listen(
+    mut reader: BufReader<TcpStream>,
+    source: usize,
+    sender: Sender<(usize, Message)>,
+) -> io::Result<()> {
+    reader.get_ref().set_read_timeout(None)?;
+    thread::spawn(move || loop {
+        match read_message(&mut reader) {
+            Ok(message) => {
+                if sender.send((source, message)).is_err() {
+                    return;
+                }
+            }
+            Err(_) => {
+                let _ = sender.send((source, Message::Bye));
+                return;
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Hands the writing half of a connection to a thread that sends whatever it
+/// is given, so the window never waits on the network. A failed write is
+/// passed on as `source` saying goodbye.
+fn speak(
+    mut stream: TcpStream,
+    source: usize,
+    sender: Sender<(usize, Message)>,
+) -> Sender<Message> {
+    let (outgoing, messages) = mpsc::channel::<Message>();
+    thread::spawn(move || {
+        for message in messages {
+            if writeln!(stream, "{}", message).is_err() {
+                let _ = sender.send((source, Message::Bye));
+                return;
+            }
+        }
+    });
+    outgoing
+}
+
+/// A guest's connection to a host, made and greeted on a background thread.
+#[derive(Debug)]
+pub struct Connecting {
+    result: Receiver<io::Result<(Peer, Variant, Setup)>>,
+}
+
+impl Connecting {
+    /// The new peer and the host's board, or why there is none, once the
+    /// handshake is over.
+    pub fn poll(&self) -> Option<io::Result<(Peer, Variant, Setup)>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                Some(Err(invalid("the handshake stopped")))
+            }
+        }
+    }
+
+    /// Waits for the handshake to end.
+    pub fn wait(self) -> io::Result<(Peer, Variant, Setup)> {
+        self.result
+            .recv()
+            .unwrap_or_else(|_| Err(invalid("the handshake stopped")))
+    }
+}
+
+/// Someone who just connected to a host, and whether they came to play or
+/// to watch.
+#[derive(Debug)]
+pub struct Visitor {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+    pub watching: bool,
+}
+
+/// Reads a newcomer's opening message.
+pub fn greet(stream: TcpStream) -> io::Result<Visitor> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let watching = match read_message(&mut reader)? {
+        Message::Hello(VERSION) => false,
+        Message::Watch(VERSION) => true,
+        Message::Hello(_) | Message::Watch(_) => {
+            return Err(invalid("the visitor speaks another version"))
+        }
+        _ => return Err(invalid("the visitor did not say hello")),
+    };
+    Ok(Visitor {
+        stream,
+        reader,
+        watching,
+    })
+}
+
+/// Where a host takes in newcomers. Each is greeted on a thread of its own,
+/// so one that connects and says nothing never holds up the window.
+#[derive(Debug)]
+pub struct Door {
+    listener: TcpListener,
+    sender: Sender<io::Result<Visitor>>,
+    arrivals: Receiver<io::Result<Visitor>>,
+}
+
+impl Door {
+    /// Starts listening at `address`.
+    pub fn open(address: impl ToSocketAddrs) -> io::Result<Door> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let (sender, arrivals) = mpsc::channel();
+        Ok(Door {
+            listener,
+            sender,
+            arrivals,
+        })
+    }
+
+    /// Where newcomers connect.
+    pub fn address(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Lets in whoever is waiting and hands back the newcomers that have
+    /// said hello since the last call, or those that failed to. Fails once
+    /// the listener stops working.
+    pub fn poll(&self) -> io::Result<Vec<io::Result<Visitor>>> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    let sender = self.sender.clone();
+                    thread::spawn(move || {
+                        let _ = sender.send(greet(stream));
+                    });
+                }
+                Err(ref error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) => return Err(error),
+            }
+        }
+        Ok(self.arrivals.try_iter().collect())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Role {
+    Host,
+    Guest,
+    Spectator,
+}
+
+/// One end of a connection. Players play `side`; spectators only follow
+/// the host's board.
+#[derive(Debug)]
+pub struct Peer {
+    pub side: Player,
+    pub role: Role,
+    /// The other side left, or the connection broke.
+    pub closed: bool,
+    /// How many spectators the host has.
+    pub spectators: usize,
+    /// The other player's profile name, once they have said it.
+    pub rival: Option<String>,
+    /// Messages for the host's opponent, or the host for everyone else.
+    outgoing: Sender<Message>,
+    incoming: Receiver<(usize, Message)>,
+    sender: Sender<(usize, Message)>,
+    /// Messages for the host's spectators, by source number.
+    watchers: Vec<(usize, Sender<Message>)>,
+    next_watcher: usize,
+    /// What the host tells newcomers: the board and every move since.
+    start: Message,
+    log: Vec<Move>,
+    /// The guest's move that is waiting on the host's answer.
+    pending: Option<Move>,
+}
+
+impl Peer {
+    /// Greets a guest and tells them which board to set up. The host plays X.
+    pub fn host(visitor: Visitor, variant: Variant, setup: Setup) -> io::Result<Peer> {
+        if visitor.watching {
+            return Err(invalid("there is no game to watch yet"));
+        }
+        let start = Message::Start(variant, setup);
+        let mut peer = Peer::new(
+            visitor.stream,
+            visitor.reader,
+            Player::Player1,
+            Role::Host,
+            start.clone(),
+        )?;
+        peer.send(Message::Hello(VERSION));
+        peer.send(start);
+        Ok(peer)
+    }
+
+    /// Starts connecting to a host to learn which board it is playing on.
+    pub fn join(address: impl ToSocketAddrs + Send + 'static) -> Connecting {
+        Peer::connect(address, Message::Hello(VERSION), Role::Guest)
+    }
+
+    /// Starts connecting to a host to follow its game without playing.
+    pub fn watch(address: impl ToSocketAddrs + Send + 'static) -> Connecting {
+        Peer::connect(address, Message::Watch(VERSION), Role::Spectator)
+    }
+
+    fn connect(
+        address: impl ToSocketAddrs + Send + 'static,
+        greeting: Message,
+        role: Role,
+    ) -> Connecting {
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            let _ = sender.send(Peer::handshake(address, greeting, role));
+        });
+        Connecting { result }
+    }
+
+    fn handshake(
+        address: impl ToSocketAddrs,
+        greeting: Message,
+        role: Role,
+    ) -> io::Result<(Peer, Variant, Setup)> {
+        let mut stream = TcpStream::connect(address)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        writeln!(stream, "{}", greeting)?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        match read_message(&mut reader)? {
+            Message::Hello(VERSION) => {}
+            Message::Hello(_) => return Err(invalid("the host speaks another version")),
+            Message::Bye => return Err(invalid("the host turned us away")),
+            _ => return Err(invalid("the host did not say hello")),
+        }
+        let start = read_message(&mut reader)?;
+        let (variant, setup) = match start {
+            Message::Start(variant, setup) => (variant, setup),
+            _ => return Err(invalid("the host did not start a game")),
+        };
+        setup
+            .check(&variant.rules())
+            .map_err(|error| invalid(&error))?;
+        let peer = Peer::new(stream, reader, Player::Player2, role, start)?;
+        Ok((peer, variant, setup))
+    }
+
+    fn new(
+        stream: TcpStream,
+        reader: BufReader<TcpStream>,
+        side: Player,
+        role: Role,
+        start: Message,
+    ) -> io::Result<Peer> {
+        let (sender, incoming) = mpsc::channel();
+        listen(reader, OPPONENT, sender.clone())?;
+        let outgoing = speak(stream, OPPONENT, sender.clone());
+        Ok(Peer {
+            side,
+            role,
+            closed: false,
+            spectators: 0,
+            rival: None,
+            outgoing,
+            incoming,
+            sender,
+            watchers: vec![],
+            next_watcher: OPPONENT + 1,
+            start,
+            log: vec![],
+            pending: None,
+        })
+    }
+
+    /// Lets a spectator in to the host's game, catching them up on the moves
+    /// so far. A second player is turned away.
+    pub fn admit(&mut self, visitor: Visitor) -> io::Result<()> {
+        let Visitor {
+            stream,
+            reader,
+            watching,
+        } = visitor;
+        if self.role != Role::Host || !watching {
+            // nobody is listening to them, so a failed goodbye goes nowhere
+            let (nowhere, _) = mpsc::channel();
+            let _ = speak(stream, OPPONENT, nowhere).send(Message::Bye);
+            return Err(invalid("the game already has two players"));
+        }
+        let source = self.next_watcher;
+        self.next_watcher += 1;
+        let watcher = speak(stream, source, self.sender.clone());
+        let catch_up = self.log.iter().map(|&play| Message::Move(play));
+        for message in vec![Message::Hello(VERSION), self.start.clone()]
+            .into_iter()
+            .chain(catch_up)
+        {
+            let _ = watcher.send(message);
+        }
+        listen(reader, source, self.sender.clone())?;
+        self.watchers.push((source, watcher));
+        self.count_watchers();
+        Ok(())
+    }
+
+    fn send(&mut self, message: Message) {
+        if self.outgoing.send(message).is_err() {
+            self.closed = true;
+        }
+    }
+
+    /// Sends `message` to every spectator, dropping the ones that are gone.
+    fn tell_watchers(&mut self, message: Message) {
+        let before = self.watchers.len();
+        self.watchers
+            .retain(|(_, watcher)| watcher.send(message.clone()).is_ok());
+        if self.watchers.len() != before {
+            self.count_watchers();
+        }
+    }
+
+    /// Lets everyone know how many spectators there are now.
+    fn count_watchers(&mut self) {
+        self.spectators = self.watchers.len();
+        let count = Message::Watchers(self.spectators);
+        self.send(count.clone());
+        self.tell_watchers(count);
+    }
+
+    /// The host plays a move that has passed its checks and passes it on to
+    /// the spectators.
+    fn record(&mut self, board: &mut Board, play: Move) {
+        board.play(play);
+        self.log.push(play);
+        self.tell_watchers(Message::Move(play));
+    }
+
+    /// Plays a move made on this machine. The host checks it and passes it
+    /// on; the guest sends it to the host and plays it once accepted.
+    pub fn play_local(
+        &mut self,
+        board: &mut Board,
+        play: Move,
+    ) -> Result<(), MoveError> {
+        if self.pending.is_some() || self.role == Role::Spectator {
+            return Err(MoveError::OutOfTurn);
+        }
+        board.check_move(play, self.side)?;
+        if self.role == Role::Host {
+            self.record(board, play);
+        } else {
+            self.pending = Some(play);
+        }
+        // a dropped connection shows up as `closed`
+        self.send(Message::Move(play));
+        Ok(())
+    }
+
+    /// Handles everything the other side sent since the last call.
+    pub fn update(&mut self, board: &mut Board) {
+        while let Ok((source, message)) = self.incoming.try_recv() {
+            self.handle(board, source, message);
+        }
+    }
+
+    fn handle(&mut self, board: &mut Board, source: usize, message: Message) {
+        if source != OPPONENT {
+            // spectators only ever get to leave
+            if message == Message::Bye {
+                self.watchers.retain(|(id, _)| *id != source);
+                self.count_watchers();
+            }
+            return;
+        }
+        match message {
+            Message::Move(play) if self.role == Role::Host => {
+                let answer = match board.check_move(play, -self.side) {
+                    Ok(()) => Message::Accepted,
+                    Err(error) => Message::Rejected(error),
+                };
+                if answer == Message::Accepted {
+                    self.record(board, play);
+                }
+                self.send(answer);
+            }
+            // the host has already checked its own moves
+            Message::Move(play) => board.play(play),
+            Message::Accepted => {
+                if let Some(play) = self.pending.take() {
+                    board.play(play);
+                }
+            }
+            Message::Rejected(_) => self.pending = None,
+            Message::Watchers(count) => self.spectators = count,
+            Message::Name(name) => self.rival = Some(name),
+            Message::Bye => self.closed = true,
+            Message::Hello(_) | Message::Watch(_) | Message::Start(..) => {}
+        }
+    }
+
+    /// Tells the other player which profile is playing on this end.
+    /// Spectators keep to themselves.
+    pub fn introduce(&mut self, name: &str) {
+        if self.role != Role::Spectator && !name.is_empty() {
+            self.send(Message::Name(name.to_string()));
+        }
+    }
+
+    /// Tells everyone connected to this end that it is leaving.
+    pub fn leave(&mut self) {
+        self.send(Message::Bye);
+        self.tell_watchers(Message::Bye);
+        self.watchers.clear();
+        self.closed = true;
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::board::BoardState;
+    #[allow(unused_imports)]
+    use crate::field::Field;
+    #[allow(unused_imports)]
+    use crate::model::PlayerMode;
+    #[allow(unused_imports)]
+    use nannou::prelude::Rect;
+    #[allow(unused_imports)]
+    use std::net::TcpListener;
+
+    #[allow(dead_code)]
+    fn new_board(variant: Variant, setup: Setup) -> Board {
+        Board::new(
+            Rect::from_w_h(600.0, 600.0),
+            PlayerMode::PlayX,
+            variant.rules(),
+        )
+        .with_setup(setup)
+    }
+
+    /// A host and guest on the given board, and the host's listener.
+    #[allow(dead_code)]
+    fn connect_on(
+        variant: Variant,
+        setup: Setup,
+    ) -> (TcpListener, Peer, Board, Peer, Board) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let guest = Peer::join(address);
+        let (stream, _) = listener.accept().unwrap();
+        let host = Peer::host(greet(stream).unwrap(), variant, setup).unwrap();
+        let (guest, guest_variant, guest_setup) = guest.wait().unwrap();
+        assert_eq!((guest_variant, guest_setup), (variant, setup));
+        let (host_board, guest_board) =
+            (new_board(variant, setup), new_board(variant, setup));
+        (listener, host, host_board, guest, guest_board)
+    }
+
+    #[allow(dead_code)]
+    fn connect(variant: Variant, setup: Setup) -> (Peer, Board, Peer, Board) {
+        let (_, host, host_board, guest, guest_board) = connect_on(variant, setup);
+        (host, host_board, guest, guest_board)
+    }
+
+    /// Waits for the next message to `peer` and handles it.
+    #[allow(dead_code)]
+    fn receive(peer: &mut Peer, board: &mut Board) -> Message {
+        let (source, message) =
+            peer.incoming.recv_timeout(Duration::from_secs(5)).unwrap();
+        peer.handle(board, source, message.clone());
+        message
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            Message::Hello(VERSION),
+            Message::Start(Variant::Hex, Setup::Random(42)),
+            Message::Start(Variant::Classic, Setup::Preset(2)),
+            Message::Move(Move::Place(4)),
+            Message::Move(Move::Slide(0, 1)),
+            Message::Accepted,
+            Message::Rejected(MoveError::Occupied),
+            Message::Watch(VERSION),
+            Message::Watchers(3),
+            Message::Name("Ada Lovelace".to_string()),
+            Message::Bye,
+        ];
+        messages.iter().for_each(|message| {
+            assert_eq!(Message::parse(&message.to_string()), Some(message.clone()));
+        });
+        assert_eq!(Message::parse("NAME"), None);
+        assert_eq!(Message::parse("START 99 EMPTY"), None);
+        assert_eq!(Message::parse("MOVE PLACE x"), None);
+    }
+    #[test]
+    fn moves_cross_localhost() {
+        let (mut host, mut host_board, mut guest, mut guest_board) =
+            connect(Variant::Classic, Setup::Empty);
+        host.play_local(&mut host_board, Move::Place(4)).unwrap();
+        receive(&mut guest, &mut guest_board);
+        assert_eq!(guest_board.board, host_board.board);
+
+        guest.play_local(&mut guest_board, Move::Place(0)).unwrap();
+        // not shown until the host says so
+        assert_eq!(guest_board.board[0], Field::Empty);
+        receive(&mut host, &mut host_board);
+        assert_eq!(receive(&mut guest, &mut guest_board), Message::Accepted);
+        assert_eq!(guest_board.board, host_board.board);
+        assert_eq!(guest_board.board[0], Field::O);
+    }
+    #[test]
+    fn host_rejects_bad_moves() {
+        let (mut host, mut host_board, mut guest, mut guest_board) =
+            connect(Variant::Classic, Setup::Empty);
+        // out of turn: X moves first
+        guest.send(Message::Move(Move::Place(0)));
+        receive(&mut host, &mut host_board);
+        assert_eq!(
+            receive(&mut guest, &mut guest_board),
+            Message::Rejected(MoveError::OutOfTurn)
+        );
+        host.play_local(&mut host_board, Move::Place(4)).unwrap();
+        receive(&mut guest, &mut guest_board);
+        // occupied
+        guest.send(Message::Move(Move::Place(4)));
+        receive(&mut host, &mut host_board);
+        assert_eq!(
+            receive(&mut guest, &mut guest_board),
+            Message::Rejected(MoveError::Occupied)
+        );
+        assert_eq!(host_board.board[4], Field::X);
+        assert_eq!(host_board.state, BoardState::InGame);
+    }
+    #[test]
+    fn players_learn_each_others_names() {
+        let (mut host, mut host_board, mut guest, mut guest_board) =
+            connect(Variant::Classic, Setup::Empty);
+        host.introduce("ann");
+        guest.introduce("bob");
+        receive(&mut host, &mut host_board);
+        receive(&mut guest, &mut guest_board);
+        assert_eq!(host.rival.as_deref(), Some("bob"));
+        assert_eq!(guest.rival.as_deref(), Some("ann"));
+    }
+    #[test]
+    fn joining_does_not_wait_for_the_host() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let guest = Peer::join(listener.local_addr().unwrap());
+        let (stream, _) = listener.accept().unwrap();
+        // the host has not said hello yet
+        assert!(guest.poll().is_none());
+        let host = Peer::host(greet(stream).unwrap(), Variant::Classic, Setup::Empty);
+        let (guest, _, _) = guest.wait().unwrap();
+        assert_eq!(
+            (host.unwrap().side, guest.side),
+            (Player::Player1, Player::Player2)
+        );
+    }
+    #[test]
+    fn silent_visitors_do_not_hold_up_the_host() {
+        let door = Door::open("127.0.0.1:0").unwrap();
+        let address = door.address().unwrap();
+        let _silent = TcpStream::connect(address).unwrap();
+        let started = std::time::Instant::now();
+        assert!(door.poll().unwrap().is_empty());
+        assert!(started.elapsed() < HANDSHAKE_TIMEOUT / 5);
+
+        // someone who does say hello still gets in meanwhile
+        let guest = Peer::join(address);
+        let visitor = loop {
+            if let Some(visitor) = door.poll().unwrap().pop() {
+                break visitor;
+            }
+            assert!(started.elapsed() < HANDSHAKE_TIMEOUT / 5);
+            thread::sleep(Duration::from_millis(5));
+        };
+        Peer::host(visitor.unwrap(), Variant::Classic, Setup::Empty).unwrap();
+        assert!(guest.wait().is_ok());
+    }
+    #[test]
+    fn leaving_closes_the_other_side() {
+        let (mut host, mut host_board, mut guest, _) =
+            connect(Variant::Classic, Setup::Empty);
+        guest.leave();
+        receive(&mut host, &mut host_board);
+        assert!(host.closed);
+    }
+    #[test]
+    fn spectators_catch_up_and_follow() {
+        let (listener, mut host, mut host_board, mut guest, mut guest_board) =
+            connect_on(Variant::Classic, Setup::Empty);
+        let address = listener.local_addr().unwrap();
+        host.play_local(&mut host_board, Move::Place(4)).unwrap();
+
+        let spectator = Peer::watch(address);
+        let (stream, _) = listener.accept().unwrap();
+        // watching waits on the host in the background, like joining
+        assert!(spectator.poll().is_none());
+        host.admit(greet(stream).unwrap()).unwrap();
+        let (mut spectator, _, _) = spectator.wait().unwrap();
+        let mut board = new_board(Variant::Classic, Setup::Empty);
+        assert_eq!(host.spectators, 1);
+        assert_eq!(
+            receive(&mut spectator, &mut board),
+            Message::Move(Move::Place(4))
+        );
+        assert_eq!(receive(&mut spectator, &mut board), Message::Watchers(1));
+        assert_eq!(
+            spectator.play_local(&mut board, Move::Place(0)),
+            Err(MoveError::OutOfTurn)
+        );
+
+        // a second player is turned away
+        let late = Peer::join(address);
+        let (stream, _) = listener.accept().unwrap();
+        assert!(host.admit(greet(stream).unwrap()).is_err());
+        assert!(late.wait().is_err());
+
+        receive(&mut guest, &mut guest_board);
+        assert_eq!(receive(&mut guest, &mut guest_board), Message::Watchers(1));
+        assert_eq!(guest.spectators, 1);
+        guest.play_local(&mut guest_board, Move::Place(0)).unwrap();
+        receive(&mut host, &mut host_board);
+        assert_eq!(
+            receive(&mut spectator, &mut board),
+            Message::Move(Move::Place(0))
+        );
+        assert_eq!(board.board, host_board.board);
+
+        spectator.leave();
+        assert_eq!(receive(&mut host, &mut host_board), Message::Bye);
+        assert_eq!(host.spectators, 0);
+        assert!(!host.closed);
+    }
+}
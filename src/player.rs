@@ -1,10 +1,13 @@
 use crate::field::Field;
 use crate::model::PlayerMode;
+use serde::{Deserialize, Serialize};
 use std::convert::From;
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum Player {
+    #[serde(rename = "x")]
     Player1 = 1,
+    #[serde(rename = "o")]
     Player2 = -1,
 }
 
@@ -31,6 +34,14 @@ impl Player {
             Player::Player2 => Field::O,
         }
     }
+
+    /// Slot of this player in per-player pairs such as `Board::queues`.
+    pub fn index(self) -> usize {
+        match self {
+            Player::Player1 => 0,
+            Player::Player2 => 1,
+        }
+    }
 }
 
 impl std::ops::Neg for Player {
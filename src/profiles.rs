@@ -0,0 +1,252 @@
+//! Named local players and their lifetime statistics, kept between runs in a
+//! JSON file.
+use crate::board::BoardState;
+use crate::json_file;
+use crate::player::Player;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+
+pub const DEFAULT_PATH: &str = "profiles.json";
+
+/// How a game was played.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PlayMode {
+    /// Against the computer in the window.
+    SinglePlayer,
+    /// Against someone at the same window, counted for both profiles.
+    HotSeat,
+    /// Against another copy of the app, see `net`.
+    Network,
+    /// On the game server, see `lobby`.
+    Server,
+}
+
+impl std::string::ToString for PlayMode {
+    fn to_string(&self) -> String {
+        match self {
+            PlayMode::SinglePlayer => "Single player".to_string(),
+            PlayMode::HotSeat => "Hot seat".to_string(),
+            PlayMode::Network => "Network".to_string(),
+            PlayMode::Server => "Server".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+impl Outcome {
+    /// How a finished game went for `side`.
+    pub fn of(result: &BoardState, side: Player) -> Option<Outcome> {
+        match result {
+            BoardState::Winner(winner, _) if *winner == side => Some(Outcome::Win),
+            BoardState::Winner(..) => Some(Outcome::Loss),
+            BoardState::Tie => Some(Outcome::Draw),
+            BoardState::InGame => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub games: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl Record {
+    fn add(&mut self, outcome: Outcome) {
+        self.games += 1;
+        match outcome {
+            Outcome::Win => self.wins += 1,
+            Outcome::Loss => self.losses += 1,
+            Outcome::Draw => self.draws += 1,
+        }
+    }
+}
+
+impl std::string::ToString for Record {
+    fn to_string(&self) -> String {
+        format!(
+            "{} games: {} won, {} lost, {} drawn",
+            self.games, self.wins, self.losses, self.draws
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    pub total: Record,
+    pub modes: BTreeMap<PlayMode, Record>,
+    /// By `Player::index`.
+    pub sides: [Record; 2],
+    /// Wins in a row if positive, losses in a row if negative.
+    pub streak: i32,
+    pub longest_winning_streak: u32,
+    /// Moves made by both sides across every game.
+    pub moves: u64,
+}
+
+impl Stats {
+    pub fn record(
+        &mut self,
+        mode: PlayMode,
+        side: Player,
+        outcome: Outcome,
+        moves: usize,
+    ) {
+        self.total.add(outcome);
+        self.modes.entry(mode).or_default().add(outcome);
+        self.sides[side.index()].add(outcome);
+        self.streak = match outcome {
+            Outcome::Win => self.streak.max(0) + 1,
+            Outcome::Loss => self.streak.min(0) - 1,
+            Outcome::Draw => 0,
+        };
+        self.longest_winning_streak =
+            self.longest_winning_streak.max(self.streak.max(0) as u32);
+        self.moves += moves as u64;
+    }
+
+    /// Moves per game, counting both sides.
+    pub fn average_length(&self) -> f64 {
+        if self.total.games == 0 {
+            return 0.0;
+        }
+        self.moves as f64 / f64::from(self.total.games)
+    }
+
+    /// A line per figure, for showing on screen.
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("All: {}", self.total.to_string())];
+        for (mode, record) in &self.modes {
+            lines.push(format!("{}: {}", mode.to_string(), record.to_string()));
+        }
+        for side in &[Player::Player1, Player::Player2] {
+            let record = &self.sides[side.index()];
+            let sigil = side.get_sigil().to_string();
+            lines.push(format!("As {}: {}", sigil, record.to_string()));
+        }
+        let streak = match self.streak {
+            0 => "none".to_string(),
+            n if n > 0 => format!("{} won", n),
+            n => format!("{} lost", -n),
+        };
+        lines.push(format!(
+            "Streak: {}, longest winning streak: {}",
+            streak, self.longest_winning_streak
+        ));
+        lines.push(format!("Average game: {:.1} moves", self.average_length()));
+        lines
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Profiles {
+    pub players: BTreeMap<String, Stats>,
+}
+
+impl Profiles {
+    /// Reads the profiles at `path`, or starts afresh if there are none yet.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Profiles> {
+        json_file::load(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        json_file::save(self, path)
+    }
+
+    /// Adds a profile with no games, keeping any that has the name already.
+    pub fn create(&mut self, name: &str) {
+        self.players.entry(name.to_string()).or_default();
+    }
+
+    /// Counts a finished game for `name`, who played `side`.
+    pub fn record(
+        &mut self,
+        name: &str,
+        mode: PlayMode,
+        side: Player,
+        result: &BoardState,
+        moves: usize,
+    ) {
+        if let Some(outcome) = Outcome::of(result, side) {
+            let stats = self.players.entry(name.to_string()).or_default();
+            stats.record(mode, side, outcome, moves);
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[test]
+    fn counts_by_mode_side_and_streak() {
+        let mut profiles = Profiles::default();
+        let x_wins = BoardState::Winner(Player::Player1, vec![0, 1, 2]);
+        profiles.record("ann", PlayMode::SinglePlayer, Player::Player1, &x_wins, 5);
+        profiles.record("ann", PlayMode::Server, Player::Player1, &x_wins, 7);
+        profiles.record("ann", PlayMode::Server, Player::Player2, &x_wins, 6);
+        profiles.record(
+            "ann",
+            PlayMode::Network,
+            Player::Player2,
+            &BoardState::Tie,
+            9,
+        );
+        profiles.record(
+            "ann",
+            PlayMode::Network,
+            Player::Player2,
+            &BoardState::InGame,
+            3,
+        );
+
+        let stats = &profiles.players["ann"];
+        assert_eq!(stats.total.games, 4);
+        assert_eq!(stats.modes[&PlayMode::Server].wins, 1);
+        assert_eq!(stats.modes[&PlayMode::Server].losses, 1);
+        assert_eq!(stats.sides[Player::Player2.index()].draws, 1);
+        assert_eq!(stats.streak, 0);
+        assert_eq!(stats.longest_winning_streak, 2);
+        assert_eq!(stats.average_length(), 27.0 / 4.0);
+        assert_eq!(stats.lines()[0], "All: 4 games: 2 won, 1 lost, 1 drawn");
+    }
+    #[test]
+    fn hot_seat_games_count_for_both_sides() {
+        let mut profiles = Profiles::default();
+        let o_wins = BoardState::Winner(Player::Player2, vec![2, 4, 6]);
+        profiles.record("ann", PlayMode::HotSeat, Player::Player1, &o_wins, 6);
+        profiles.record("bob", PlayMode::HotSeat, Player::Player2, &o_wins, 6);
+        assert_eq!(profiles.players["ann"].modes[&PlayMode::HotSeat].losses, 1);
+        assert_eq!(profiles.players["bob"].modes[&PlayMode::HotSeat].wins, 1);
+        assert_eq!(profiles.players["bob"].streak, 1);
+        assert_eq!(
+            profiles.players["bob"].lines()[1],
+            "Hot seat: 1 games: 1 won, 0 lost, 0 drawn"
+        );
+    }
+    #[test]
+    fn survives_a_round_trip() {
+        let mut profiles = Profiles::default();
+        profiles.create("bob");
+        profiles.record(
+            "bob",
+            PlayMode::Server,
+            Player::Player2,
+            &BoardState::Tie,
+            9,
+        );
+        let json = serde_json::to_string(&profiles).unwrap();
+        assert!(json.contains("\"server\""));
+        assert_eq!(serde_json::from_str::<Profiles>(&json).unwrap(), profiles);
+    }
+}
@@ -0,0 +1,118 @@
+//! The JSON spoken between the game server and its clients over WebSocket.
+//!
+//! Every message is an object with a `type`. Clients send:
+//!
+//! - `{"type": "create", "variant": "hex", "side": "x", "bot": true,
+//!   "difficulty": "easy", "private": false}` starts a game and seats the
+//!   sender. Everything but the type is optional; with `bot` the computer
+//!   takes the other side. Private games are left out of the list and are
+//!   joined with the code the server hands back.
+//! - `{"type": "join", "game": 1}` or `{"type": "join", "code": "042137"}`
+//!   takes the open seat of a game.
+//! - `{"type": "quick-match", "variant": "classic"}` pairs the sender with
+//!   the next player asking for the same variant.
+//! - Each of these may also carry `"name": "ada"`, the player's profile,
+//!   so both players' names are known for rating the game.
+//! - `{"type": "list"}` asks for the public games with a seat open.
+//! - `{"type": "move", "game": 1, "move": {"place": 4}}`, or
+//!   `{"slide": [0, 1]}` for a slide, plays for the sender's seat.
+//! - `{"type": "state", "game": 1}` asks for the current position.
+//!
+//! The server answers with `seated` when a client sits down, `waiting` while
+//! a quick match looks for an opponent, `games` with the list, and `error`
+//! when a request fails. It pushes `state` to everyone in a game whenever it
+//! changes, with the `players` seated by name, and `left` when a player goes
+//! away, leaving their seat open.
+use crate::brain::Difficulty;
+use crate::games::GameState;
+use crate::player::Player;
+use crate::rules::{Move, Variant};
+use crate::setup::Setup;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Request {
+    Create {
+        #[serde(default)]
+        variant: Variant,
+        #[serde(default)]
+        setup: Setup,
+        side: Option<Player>,
+        #[serde(default)]
+        bot: bool,
+        #[serde(default)]
+        difficulty: Difficulty,
+        #[serde(default)]
+        private: bool,
+        name: Option<String>,
+    },
+    Join {
+        game: Option<u64>,
+        code: Option<String>,
+        name: Option<String>,
+    },
+    QuickMatch {
+        #[serde(default)]
+        variant: Variant,
+        name: Option<String>,
+    },
+    List,
+    Move {
+        game: u64,
+        #[serde(rename = "move")]
+        play: Move,
+    },
+    State {
+        game: u64,
+    },
+}
+
+/// A public game waiting for a second player.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Listing {
+    pub game: u64,
+    pub variant: Variant,
+    /// The side whoever joins will play.
+    pub side: Player,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Reply {
+    Seated {
+        game: u64,
+        side: Player,
+        /// Set for private games, to hand to the other player.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<String>,
+    },
+    Waiting,
+    Games {
+        games: Vec<Listing>,
+    },
+    State {
+        game: u64,
+        /// The names of the players in each seat, by `Player::index`, for
+        /// those who gave one.
+        #[serde(default)]
+        players: [Option<String>; 2],
+        #[serde(flatten)]
+        state: Box<GameState>,
+    },
+    Left {
+        game: u64,
+        side: Player,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl Reply {
+    pub fn error(message: impl ToString) -> Reply {
+        Reply::Error {
+            message: message.to_string(),
+        }
+    }
+}
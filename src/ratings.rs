@@ -0,0 +1,191 @@
+//! Elo ratings for people and bots, kept between runs in a JSON file.
+use crate::board::BoardState;
+use crate::brain::Difficulty;
+use crate::json_file;
+use crate::player::Player;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub const DEFAULT_PATH: &str = "ratings.json";
+
+/// Where the GUI writes the leaderboard for other tools.
+pub const EXPORT_PATH: &str = "ratings.csv";
+
+/// Everyone starts here.
+pub const INITIAL_RATING: f64 = 1500.0;
+
+/// How far one game can move a rating.
+const K_FACTOR: f64 = 32.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rating {
+    pub rating: f64,
+    pub games: u32,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating {
+            rating: INITIAL_RATING,
+            games: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ratings {
+    /// By name; bots go by `bot_name`.
+    pub players: BTreeMap<String, Rating>,
+}
+
+/// The name a bot is rated under. Each difficulty is its own player.
+pub fn bot_name(difficulty: Difficulty) -> String {
+    format!("Bot ({})", difficulty.to_string())
+}
+
+/// X's and O's names in a game `name` played as `side` against `rival`, or
+/// `None` when it can't be rated because someone is unnamed or the same
+/// person sat on both sides.
+pub fn pairing<'a>(
+    name: &'a str,
+    side: Player,
+    rival: &'a str,
+) -> Option<(&'a str, &'a str)> {
+    if name.is_empty() || rival.is_empty() || name == rival {
+        return None;
+    }
+    match side {
+        Player::Player1 => Some((name, rival)),
+        Player::Player2 => Some((rival, name)),
+    }
+}
+
+/// The score `rating` is expected to take against `other`, from 0 to 1.
+pub fn expected(rating: f64, other: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((other - rating) / 400.0))
+}
+
+impl Ratings {
+    /// Reads the ratings at `path`, or starts afresh if there are none yet.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Ratings> {
+        json_file::load(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        json_file::save(self, path)
+    }
+
+    pub fn get(&self, name: &str) -> Rating {
+        self.players.get(name).copied().unwrap_or_default()
+    }
+
+    /// Updates both players after a finished game between `x` and `o`. Games
+    /// still in progress change nothing.
+    pub fn record(&mut self, x: &str, o: &str, result: &BoardState) {
+        let score = match result {
+            BoardState::Winner(Player::Player1, _) => 1.0,
+            BoardState::Winner(Player::Player2, _) => 0.0,
+            BoardState::Tie => 0.5,
+            BoardState::InGame => return,
+        };
+        let (old_x, old_o) = (self.get(x), self.get(o));
+        let change = K_FACTOR * (score - expected(old_x.rating, old_o.rating));
+        for (name, old, change) in &[(x, old_x, change), (o, old_o, -change)] {
+            self.players.insert(
+                name.to_string(),
+                Rating {
+                    rating: old.rating + change,
+                    games: old.games + 1,
+                },
+            );
+        }
+    }
+
+    /// Everyone by name and rating, strongest first.
+    pub fn leaderboard(&self) -> Vec<(&str, Rating)> {
+        let mut board: Vec<(&str, Rating)> = self
+            .players
+            .iter()
+            .map(|(name, rating)| (name.as_str(), *rating))
+            .collect();
+        board.sort_by(|a, b| b.1.rating.partial_cmp(&a.1.rating).unwrap());
+        board
+    }
+
+    /// Writes the leaderboard as CSV, one player per line.
+    pub fn export_csv(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "rank,name,rating,games")?;
+        for (rank, (name, rating)) in self.leaderboard().iter().enumerate() {
+            // names are typed by people, so quote them
+            let name = name.replace('"', "\"\"");
+            writeln!(
+                out,
+                "{},\"{}\",{:.0},{}",
+                rank + 1,
+                name,
+                rating.rating,
+                rating.games
+            )?;
+        }
+        Ok(())
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[test]
+    fn winner_takes_what_the_loser_gives() {
+        let mut ratings = Ratings::default();
+        let win = BoardState::Winner(Player::Player2, vec![0, 1, 2]);
+        ratings.record("ann", "bob", &win);
+        assert_eq!(ratings.get("bob").rating, INITIAL_RATING + 16.0);
+        assert_eq!(ratings.get("ann").rating, INITIAL_RATING - 16.0);
+        assert_eq!(ratings.get("ann").games, 1);
+
+        // beating a weaker player earns less
+        ratings.record("bob", "cat", &win);
+        let gain = ratings.get("cat").rating - INITIAL_RATING;
+        assert!(gain > 16.0);
+        ratings.record("ann", "bob", &BoardState::InGame);
+        assert_eq!(ratings.get("ann").games, 1);
+    }
+    #[test]
+    fn equals_who_tie_stay_put() {
+        let mut ratings = Ratings::default();
+        ratings.record("ann", &bot_name(Difficulty::Easy), &BoardState::Tie);
+        assert_eq!(ratings.get("ann").rating, INITIAL_RATING);
+        assert_eq!(ratings.get("Bot (Easy)").games, 1);
+        assert!(
+            (expected(1600.0, 1400.0) + expected(1400.0, 1600.0) - 1.0).abs() < 1e-9
+        );
+    }
+    #[test]
+    fn exports_strongest_first() {
+        let mut ratings = Ratings::default();
+        let win = BoardState::Winner(Player::Player1, vec![0, 4, 8]);
+        ratings.record("ann", "bob \"the bot\"", &win);
+        let mut csv = vec![];
+        ratings.export_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[1], "1,\"ann\",1516,1");
+        assert_eq!(lines[2], "2,\"bob \"\"the bot\"\"\",1484,1");
+
+        let json = serde_json::to_string(&ratings).unwrap();
+        assert_eq!(serde_json::from_str::<Ratings>(&json).unwrap(), ratings);
+    }
+    #[test]
+    fn games_between_two_named_people_are_rated() {
+        assert_eq!(pairing("ann", Player::Player2, "bob"), Some(("bob", "ann")));
+        assert_eq!(pairing("ann", Player::Player1, ""), None);
+        assert_eq!(pairing("ann", Player::Player1, "ann"), None);
+        let mut ratings = Ratings::default();
+        let (x, o) = pairing("ann", Player::Player1, "bob").unwrap();
+        ratings.record(x, o, &BoardState::Winner(Player::Player1, vec![]));
+        assert_eq!(ratings.get("ann").rating, INITIAL_RATING + 16.0);
+        assert_eq!(ratings.get("bob").games, 1);
+    }
+}
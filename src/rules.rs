@@ -0,0 +1,439 @@
+use crate::field::Field;
+use crate::player::Player;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+/// The board setups that can be picked from the main menu.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Variant {
+    Classic,
+    Gravity,
+    ConnectFour,
+    ThreeMensMorris,
+    Infinite,
+    Torus,
+    Hex,
+}
+
+/// Every variant, in menu order.
+pub const VARIANTS: &[Variant] = &[
+    Variant::Classic,
+    Variant::Gravity,
+    Variant::ConnectFour,
+    Variant::ThreeMensMorris,
+    Variant::Infinite,
+    Variant::Torus,
+    Variant::Hex,
+];
+
+impl Default for Variant {
+    fn default() -> Self {
+        Variant::Classic
+    }
+}
+
+impl Variant {
+    pub fn rules(self) -> Rules {
+        match self {
+            Variant::Classic => Rules::classic(),
+            Variant::Gravity => Rules::gravity(),
+            Variant::ConnectFour => Rules::connect_four(),
+            Variant::ThreeMensMorris => Rules::three_mens_morris(),
+            Variant::Infinite => Rules::infinite(),
+            Variant::Torus => Rules::torus(),
+            Variant::Hex => Rules::hex(),
+        }
+    }
+
+    /// The variant shown after this one when cycling through the menu.
+    pub fn next(self) -> Variant {
+        match self {
+            Variant::Classic => Variant::Gravity,
+            Variant::Gravity => Variant::ConnectFour,
+            Variant::ConnectFour => Variant::ThreeMensMorris,
+            Variant::ThreeMensMorris => Variant::Infinite,
+            Variant::Infinite => Variant::Torus,
+            Variant::Torus => Variant::Hex,
+            Variant::Hex => Variant::Classic,
+        }
+    }
+}
+
+impl std::string::ToString for Variant {
+    fn to_string(&self) -> String {
+        match self {
+            Variant::Classic => "Classic".to_string(),
+            Variant::Gravity => "Gravity".to_string(),
+            Variant::ConnectFour => "Connect Four".to_string(),
+            Variant::ThreeMensMorris => "Three Men's Morris".to_string(),
+            Variant::Infinite => "Infinite".to_string(),
+            Variant::Torus => "Torus".to_string(),
+            Variant::Hex => "Hex".to_string(),
+        }
+    }
+}
+
+/// A single turn: marking an empty cell, or sliding one of your own pieces
+/// from one cell to another.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Move {
+    Place(usize),
+    Slide(usize, usize),
+}
+
+impl Move {
+    /// The cell the piece ends up in.
+    pub fn target(self) -> usize {
+        match self {
+            Move::Place(to) | Move::Slide(_, to) => to,
+        }
+    }
+
+    pub fn apply(self, board: &mut [Field], sigil: Field) {
+        if let Move::Slide(from, _) = self {
+            board[from] = Field::Empty;
+        }
+        board[self.target()] = sigil;
+    }
+
+    pub fn undo(self, board: &mut [Field], sigil: Field) {
+        board[self.target()] = Field::Empty;
+        if let Move::Slide(from, _) = self {
+            board[from] = sigil;
+        }
+    }
+}
+
+/// Board dimensions and the rules used to play on them.
+///
+/// Cells are stored row by row, starting at the top left corner.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Rules {
+    pub width: usize,
+    pub height: usize,
+    pub win_length: usize,
+    /// Pieces fall to the lowest empty cell of the clicked column.
+    pub gravity: bool,
+    /// Lines run off one edge of the board and carry on from the opposite one.
+    pub torus: bool,
+    /// Cells are hexagons, see `hex`, and lines run along the three hex axes.
+    pub hex: bool,
+    /// Once a side has this many pieces on the board it slides them to
+    /// adjacent cells instead of placing new ones.
+    pub slide_after: Option<usize>,
+    /// A side may only have this many marks on the board; placing another
+    /// removes its oldest one.
+    pub vanish_after: Option<usize>,
+    /// How many plies the computer looks ahead before estimating a position.
+    pub search_depth: i64,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules::classic()
+    }
+}
+
+impl Rules {
+    pub fn classic() -> Self {
+        Rules {
+            width: 3,
+            height: 3,
+            win_length: 3,
+            gravity: false,
+            torus: false,
+            hex: false,
+            slide_after: None,
+            vanish_after: None,
+            search_depth: 9,
+        }
+    }
+
+    pub fn gravity() -> Self {
+        Rules {
+            gravity: true,
+            ..Rules::classic()
+        }
+    }
+
+    pub fn connect_four() -> Self {
+        Rules {
+            width: 7,
+            height: 6,
+            win_length: 4,
+            gravity: true,
+            torus: false,
+            hex: false,
+            slide_after: None,
+            vanish_after: None,
+            search_depth: 5,
+        }
+    }
+
+    pub fn three_mens_morris() -> Self {
+        Rules {
+            slide_after: Some(3),
+            search_depth: 8,
+            ..Rules::classic()
+        }
+    }
+
+    pub fn infinite() -> Self {
+        Rules {
+            vanish_after: Some(3),
+            search_depth: 8,
+            ..Rules::classic()
+        }
+    }
+
+    pub fn torus() -> Self {
+        Rules {
+            width: 5,
+            height: 5,
+            win_length: 4,
+            torus: true,
+            search_depth: 4,
+            ..Rules::classic()
+        }
+    }
+
+    pub fn hex() -> Self {
+        Rules {
+            width: 5,
+            height: 5,
+            win_length: 4,
+            hex: true,
+            search_depth: 4,
+            ..Rules::classic()
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub fn column(&self, cell: usize) -> usize {
+        cell % self.width
+    }
+
+    pub fn row(&self, cell: usize) -> usize {
+        cell / self.width
+    }
+
+    pub fn cell(&self, column: usize, row: usize) -> usize {
+        row * self.width + column
+    }
+
+    /// Every run of `win_length` cells along a row, column or diagonal, in order.
+    /// On a torus, runs wrap around the edges. On a hex board the third
+    /// direction runs south-west, and there is no fourth.
+    pub fn winning_lines(&self) -> Vec<Vec<usize>> {
+        let directions: &[(isize, isize)] = if self.hex {
+            &[(1, 0), (0, 1), (-1, 1)]
+        } else {
+            &[(1, 0), (0, 1), (1, 1), (-1, 1)]
+        };
+        let (width, height) = (self.width as isize, self.height as isize);
+        let reach = self.win_length as isize - 1;
+        let mut lines = Vec::new();
+        let mut seen = HashSet::new();
+        for (dx, dy) in directions.iter() {
+            for row in 0..height {
+                for column in 0..width {
+                    let (end_column, end_row) = (column + dx * reach, row + dy * reach);
+                    if !self.torus
+                        && (end_column < 0 || end_column >= width || end_row >= height)
+                    {
+                        continue;
+                    }
+                    let line: Vec<usize> = (0..self.win_length as isize)
+                        .map(|i| {
+                            self.cell(
+                                (column + dx * i).rem_euclid(width) as usize,
+                                (row + dy * i).rem_euclid(height) as usize,
+                            )
+                        })
+                        .collect();
+                    // a wrapped run can come back onto itself, or cover the
+                    // same cells as a run starting elsewhere in the loop
+                    let mut cells = line.clone();
+                    cells.sort();
+                    cells.dedup();
+                    if cells.len() == line.len() && seen.insert(cells) {
+                        lines.push(line);
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    /// The direction, in columns and rows, of a single step between two
+    /// neighbouring cells of a line, allowing for wrapped steps on a torus.
+    pub fn step(&self, from: usize, to: usize) -> (isize, isize) {
+        let wrap = |d: isize, size: usize| -> isize {
+            let size = size as isize;
+            match d.rem_euclid(size) {
+                0 => 0,
+                1 => 1,
+                d if d == size - 1 => -1,
+                d => d,
+            }
+        };
+        let dc = self.column(to) as isize - self.column(from) as isize;
+        let dr = self.row(to) as isize - self.row(from) as isize;
+        (wrap(dc, self.width), wrap(dr, self.height))
+    }
+
+    /// Where a piece dropped into `column` comes to rest: on top of the
+    /// first piece or blocked cell in its way, if the column isn't full.
+    pub fn drop_target(&self, board: &[Field], column: usize) -> Option<usize> {
+        (0..self.height)
+            .map(|row| self.cell(column, row))
+            .take_while(|&cell| board[cell] == Field::Empty)
+            .last()
+    }
+
+    /// The cell a mark ends up in when `cell` is picked, if that is a legal move.
+    pub fn target(&self, board: &[Field], cell: usize) -> Option<usize> {
+        if self.gravity {
+            self.drop_target(board, self.column(cell))
+        } else if board[cell] == Field::Empty {
+            Some(cell)
+        } else {
+            None
+        }
+    }
+
+    /// Whether `player` is past placing pieces and has to slide them.
+    pub fn is_sliding(&self, board: &[Field], player: Player) -> bool {
+        match self.slide_after {
+            Some(pieces) => {
+                board.iter().filter(|&&v| v == player.get_sigil()).count() >= pieces
+            }
+            None => false,
+        }
+    }
+
+    /// Whether a position can come back, so games may go on forever.
+    pub fn can_repeat(&self) -> bool {
+        self.slide_after.is_some() || self.vanish_after.is_some()
+    }
+
+    /// The mark that disappears when the owner of `queue` places another one.
+    pub fn vanishing(&self, queue: &VecDeque<usize>) -> Option<usize> {
+        match self.vanish_after {
+            Some(marks) if queue.len() >= marks => queue.front().copied(),
+            _ => None,
+        }
+    }
+
+    /// Plays `play` for the owner of `queue`, which lists their marks oldest
+    /// first, and returns the mark that disappeared to make room, if any.
+    pub fn make_move(
+        &self,
+        board: &mut [Field],
+        queue: &mut VecDeque<usize>,
+        play: Move,
+        sigil: Field,
+    ) -> Option<usize> {
+        let vanished = match play {
+            Move::Place(to) => {
+                let vanished = self.vanishing(queue);
+                if let Some(oldest) = vanished {
+                    queue.pop_front();
+                    board[oldest] = Field::Empty;
+                }
+                queue.push_back(to);
+                vanished
+            }
+            Move::Slide(from, to) => {
+                if let Some(mark) = queue.iter_mut().find(|mark| **mark == from) {
+                    *mark = to;
+                }
+                None
+            }
+        };
+        play.apply(board, sigil);
+        vanished
+    }
+
+    /// Takes back a move made with `make_move`.
+    pub fn unmake_move(
+        &self,
+        board: &mut [Field],
+        queue: &mut VecDeque<usize>,
+        play: Move,
+        sigil: Field,
+        vanished: Option<usize>,
+    ) {
+        play.undo(board, sigil);
+        match play {
+            Move::Place(_) => {
+                queue.pop_back();
+                if let Some(oldest) = vanished {
+                    queue.push_front(oldest);
+                    board[oldest] = sigil;
+                }
+            }
+            Move::Slide(from, to) => {
+                if let Some(mark) = queue.iter_mut().find(|mark| **mark == to) {
+                    *mark = from;
+                }
+            }
+        }
+    }
+
+    /// Whether a piece may slide between the two cells. Steps run along rows
+    /// and columns, and along the two long diagonals.
+    pub fn adjacent(&self, a: usize, b: usize) -> bool {
+        let (ac, ar) = (self.column(a) as isize, self.row(a) as isize);
+        let (bc, br) = (self.column(b) as isize, self.row(b) as isize);
+        let (dc, dr) = ((ac - bc).abs(), (ar - br).abs());
+        let last = self.width as isize - 1;
+        match (dc, dr) {
+            (1, 0) | (0, 1) => true,
+            (1, 1) => (ac == ar && bc == br) || (ac + ar == last && bc + br == last),
+            _ => false,
+        }
+    }
+
+    /// Every move `player` may make, most promising first.
+    pub fn legal_moves(&self, board: &[Field], player: Player) -> Vec<Move> {
+        if self.is_sliding(board, player) {
+            let sigil = player.get_sigil();
+            (0..self.size())
+                .filter(|&from| board[from] == sigil)
+                .flat_map(|from| {
+                    (0..self.size())
+                        .filter(move |&to| board[to] == Field::Empty)
+                        .filter(move |&to| self.adjacent(from, to))
+                        .map(move |to| Move::Slide(from, to))
+                })
+                .collect()
+        } else {
+            self.legal_placements(board)
+                .into_iter()
+                .map(Move::Place)
+                .collect()
+        }
+    }
+
+    fn legal_placements(&self, board: &[Field]) -> Vec<usize> {
+        if self.gravity {
+            let mut columns: Vec<usize> = (0..self.width).collect();
+            let center = self.width as isize / 2;
+            columns.sort_by_key(|&column| (column as isize - center).abs());
+            columns
+                .into_iter()
+                .filter_map(|column| self.drop_target(board, column))
+                .collect()
+        } else {
+            (0..self.size())
+                .filter(|&cell| board[cell] == Field::Empty)
+                .collect()
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! The screens of the app and the ways between them. `Screen::next` says
+//! where each event leads from each screen; `Model` does whatever goes with
+//! the move, like connecting or setting up a board, and then follows it.
+use crate::config::StartMode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Screen {
+    /// Picking who is playing, see `profiles`.
+    Profiles,
+    MainMenu,
+    /// Picking whether to play first or second against the computer.
+    SideSelect,
+    /// The board, the clock, the theme and the computer's strength.
+    Settings,
+    /// The leaderboard, see `ratings`.
+    Ratings,
+    /// The current profile's statistics.
+    Stats,
+    /// Finding a game on the server, see `lobby`.
+    Lobby,
+    /// Waiting for another copy of the app to join.
+    Hosting,
+    /// A game taking moves.
+    Playing(Opponent),
+    /// A finished game, still on the board.
+    GameOver(Opponent),
+    /// Going back over a finished game, see `analysis`.
+    Analysis(Opponent),
+}
+
+/// Who a game is played against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opponent {
+    Computer,
+    /// Someone sharing this window.
+    HotSeat,
+    /// Another copy of the app, see `net`.
+    Online,
+    /// Someone on the game server, see `lobby`.
+    Server,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// The back button or the menu key.
+    Back,
+    /// A profile has been picked; the settings say where to start.
+    Start(StartMode),
+    PlayComputer,
+    ChooseSide,
+    OpenLobby,
+    OpenSettings,
+    OpenRatings,
+    OpenStats,
+    SwitchProfile,
+    HotSeat,
+    Host,
+    /// Joined or watched a host, or a guest turned up while hosting.
+    Connect,
+    /// The server has given this side a seat in a game.
+    Seated,
+    /// The game on the board has ended.
+    Finish,
+    /// Another game against the same opponent.
+    Rematch,
+    /// Going back over the game that has just ended.
+    Analyze,
+    /// The connection to the other side or the server is gone.
+    Disconnect,
+}
+
+impl Screen {
+    /// The screen `event` leads to. Events that mean nothing on this screen
+    /// leave it where it is.
+    pub fn next(self, event: Event) -> Screen {
+        use Screen::*;
+        match (self, event) {
+            (_, Event::Back) => self.back(),
+            (Profiles, Event::Start(mode)) => match mode {
+                StartMode::Menu => MainMenu,
+                StartMode::SinglePlayer => SideSelect,
+                StartMode::HotSeat => Playing(Opponent::HotSeat),
+                StartMode::Lobby => Lobby,
+            },
+            (MainMenu, Event::PlayComputer) => SideSelect,
+            (MainMenu, Event::OpenLobby) => Lobby,
+            (MainMenu, Event::OpenSettings) => Settings,
+            (MainMenu, Event::OpenRatings) => Ratings,
+            (MainMenu, Event::OpenStats) => Stats,
+            (MainMenu, Event::Host) => Hosting,
+            (MainMenu, Event::Connect) | (Hosting, Event::Connect) => {
+                Playing(Opponent::Online)
+            }
+            (SideSelect, Event::ChooseSide) => Playing(Opponent::Computer),
+            (Stats, Event::SwitchProfile) => Profiles,
+            (Lobby, Event::HotSeat) => Playing(Opponent::HotSeat),
+            (Lobby, Event::Seated) => Playing(Opponent::Server),
+            (Playing(opponent), Event::Finish) => GameOver(opponent),
+            (GameOver(opponent), Event::Rematch)
+                if opponent == Opponent::Computer || opponent == Opponent::HotSeat =>
+            {
+                Playing(opponent)
+            }
+            (GameOver(opponent), Event::Analyze) => Analysis(opponent),
+            (Hosting, Event::Disconnect) => MainMenu,
+            (Playing(opponent), Event::Disconnect)
+            | (GameOver(opponent), Event::Disconnect)
+                if opponent == Opponent::Online || opponent == Opponent::Server =>
+            {
+                self.back()
+            }
+            (screen, _) => screen,
+        }
+    }
+
+    /// Where the back button and the menu key lead: out of an analysis to
+    /// the game it is of, out of a server game to the lobby, and from
+    /// everywhere else to the main menu.
+    pub fn back(self) -> Screen {
+        match self {
+            Screen::Analysis(opponent) => Screen::GameOver(opponent),
+            Screen::Playing(Opponent::Server) | Screen::GameOver(Opponent::Server) => {
+                Screen::Lobby
+            }
+            _ => Screen::MainMenu,
+        }
+    }
+
+    /// Who the game on the board is against, on the screens that show one.
+    pub fn opponent(self) -> Option<Opponent> {
+        match self {
+            Screen::Playing(opponent)
+            | Screen::GameOver(opponent)
+            | Screen::Analysis(opponent) => Some(opponent),
+            _ => None,
+        }
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn picking_a_profile_goes_where_the_settings_say() {
+        let start = |mode| Screen::Profiles.next(Event::Start(mode));
+        assert_eq!(start(StartMode::Menu), Screen::MainMenu);
+        assert_eq!(start(StartMode::SinglePlayer), Screen::SideSelect);
+        assert_eq!(
+            start(StartMode::HotSeat),
+            Screen::Playing(Opponent::HotSeat)
+        );
+        assert_eq!(start(StartMode::Lobby), Screen::Lobby);
+        assert_eq!(
+            Screen::MainMenu.next(Event::Start(StartMode::Lobby)),
+            Screen::MainMenu
+        );
+    }
+    #[test]
+    fn the_main_menu_opens_the_other_screens() {
+        let open = |event| Screen::MainMenu.next(event);
+        assert_eq!(open(Event::PlayComputer), Screen::SideSelect);
+        assert_eq!(open(Event::OpenLobby), Screen::Lobby);
+        assert_eq!(open(Event::OpenSettings), Screen::Settings);
+        assert_eq!(open(Event::OpenRatings), Screen::Ratings);
+        assert_eq!(open(Event::OpenStats), Screen::Stats);
+        assert_eq!(open(Event::Host), Screen::Hosting);
+        assert_eq!(open(Event::Connect), Screen::Playing(Opponent::Online));
+        assert_eq!(open(Event::Finish), Screen::MainMenu);
+    }
+    #[test]
+    fn only_a_side_starts_a_game_against_the_computer() {
+        assert_eq!(
+            Screen::SideSelect.next(Event::ChooseSide),
+            Screen::Playing(Opponent::Computer)
+        );
+        assert_eq!(Screen::SideSelect.next(Event::Connect), Screen::SideSelect);
+        assert_eq!(Screen::SideSelect.next(Event::Back), Screen::MainMenu);
+    }
+    #[test]
+    fn the_statistics_switch_profiles() {
+        assert_eq!(Screen::Stats.next(Event::SwitchProfile), Screen::Profiles);
+        assert_eq!(Screen::Ratings.next(Event::SwitchProfile), Screen::Ratings);
+    }
+    #[test]
+    fn the_lobby_starts_hot_seat_and_server_games() {
+        assert_eq!(
+            Screen::Lobby.next(Event::HotSeat),
+            Screen::Playing(Opponent::HotSeat)
+        );
+        assert_eq!(
+            Screen::Lobby.next(Event::Seated),
+            Screen::Playing(Opponent::Server)
+        );
+        assert_eq!(Screen::MainMenu.next(Event::Seated), Screen::MainMenu);
+    }
+    #[test]
+    fn a_guest_turns_hosting_into_a_game() {
+        assert_eq!(
+            Screen::Hosting.next(Event::Connect),
+            Screen::Playing(Opponent::Online)
+        );
+        assert_eq!(Screen::Hosting.next(Event::Disconnect), Screen::MainMenu);
+    }
+    #[test]
+    fn a_finished_game_stays_on_the_board() {
+        for &opponent in &[
+            Opponent::Computer,
+            Opponent::HotSeat,
+            Opponent::Online,
+            Opponent::Server,
+        ] {
+            let over = Screen::Playing(opponent).next(Event::Finish);
+            assert_eq!(over, Screen::GameOver(opponent));
+            assert_eq!(over.next(Event::Finish), over);
+            assert_eq!(over.opponent(), Some(opponent));
+        }
+    }
+    #[test]
+    fn only_local_games_have_rematches() {
+        for &opponent in &[Opponent::Computer, Opponent::HotSeat] {
+            let over = Screen::GameOver(opponent);
+            assert_eq!(over.next(Event::Rematch), Screen::Playing(opponent));
+        }
+        for &opponent in &[Opponent::Online, Opponent::Server] {
+            let over = Screen::GameOver(opponent);
+            assert_eq!(over.next(Event::Rematch), over);
+        }
+        assert_eq!(
+            Screen::Playing(Opponent::Computer).next(Event::Rematch),
+            Screen::Playing(Opponent::Computer)
+        );
+    }
+    #[test]
+    fn an_analysis_goes_back_to_its_game() {
+        let over = Screen::GameOver(Opponent::Server);
+        let analysis = over.next(Event::Analyze);
+        assert_eq!(analysis, Screen::Analysis(Opponent::Server));
+        assert_eq!(analysis.opponent(), Some(Opponent::Server));
+        assert_eq!(analysis.next(Event::Back), over);
+        assert_eq!(analysis.next(Event::Disconnect), analysis);
+        assert_eq!(over.next(Event::Disconnect), Screen::Lobby);
+    }
+    #[test]
+    fn losing_the_connection_ends_network_games_only() {
+        assert_eq!(
+            Screen::Playing(Opponent::Online).next(Event::Disconnect),
+            Screen::MainMenu
+        );
+        assert_eq!(
+            Screen::GameOver(Opponent::Server).next(Event::Disconnect),
+            Screen::Lobby
+        );
+        assert_eq!(
+            Screen::Playing(Opponent::Computer).next(Event::Disconnect),
+            Screen::Playing(Opponent::Computer)
+        );
+    }
+    #[test]
+    fn back_leads_to_the_main_menu_or_the_lobby() {
+        for &screen in &[
+            Screen::Profiles,
+            Screen::MainMenu,
+            Screen::SideSelect,
+            Screen::Settings,
+            Screen::Ratings,
+            Screen::Stats,
+            Screen::Lobby,
+            Screen::Hosting,
+            Screen::Playing(Opponent::Computer),
+            Screen::GameOver(Opponent::HotSeat),
+            Screen::Playing(Opponent::Online),
+        ] {
+            assert_eq!(screen.next(Event::Back), Screen::MainMenu, "{:?}", screen);
+        }
+        assert_eq!(
+            Screen::Playing(Opponent::Server).next(Event::Back),
+            Screen::Lobby
+        );
+    }
+}
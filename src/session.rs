@@ -0,0 +1,175 @@
+//! What has happened since the app was started. Unlike `profiles` and
+//! `ratings`, none of it is saved.
+use crate::board::BoardState;
+use crate::player::Player;
+use std::cmp::Ordering;
+use std::fmt;
+
+/// Games won by each side and drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score {
+    pub x: u32,
+    pub o: u32,
+    pub draws: u32,
+}
+
+impl Score {
+    /// Counts a finished game. Games still going aren't counted.
+    pub fn record(&mut self, state: &BoardState) {
+        match state {
+            BoardState::Winner(Player::Player1, _) => self.x += 1,
+            BoardState::Winner(Player::Player2, _) => self.o += 1,
+            BoardState::Tie => self.draws += 1,
+            BoardState::InGame => {}
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "X {}   O {}   Draws {}", self.x, self.o, self.draws)
+    }
+}
+
+/// The match lengths the settings cycle through. One is a single game.
+pub const MATCH_LENGTHS: [u32; 4] = [1, 3, 5, 7];
+
+/// The match length shown after `best_of` when cycling through the settings.
+pub fn next_length(best_of: u32) -> u32 {
+    let index = MATCH_LENGTHS
+        .iter()
+        .position(|&l| l == best_of)
+        .unwrap_or(0);
+    MATCH_LENGTHS[(index + 1) % MATCH_LENGTHS.len()]
+}
+
+/// How a match length reads in the settings.
+pub fn length_name(best_of: u32) -> String {
+    match best_of {
+        1 => "Single game".to_string(),
+        _ => format!("Best of {}", best_of),
+    }
+}
+
+/// A match of several games against the same opponent, each on a fresh
+/// board, with the sides taking turns to move first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Series {
+    pub best_of: u32,
+    /// The match's games finished so far.
+    pub score: Score,
+}
+
+impl Series {
+    pub fn new(best_of: u32) -> Series {
+        Series {
+            best_of,
+            score: Score::default(),
+        }
+    }
+
+    pub fn played(&self) -> u32 {
+        self.score.x + self.score.o + self.score.draws
+    }
+
+    /// Counts a finished game, unless the match was already over.
+    pub fn record(&mut self, state: &BoardState) {
+        if !self.over() {
+            self.score.record(state);
+        }
+    }
+
+    /// Whether every game has been played, or one side is further ahead
+    /// than the games left could make up.
+    pub fn over(&self) -> bool {
+        let left = self.best_of.saturating_sub(self.played());
+        let Score { x, o, .. } = self.score;
+        left == 0 || x > o + left || o > x + left
+    }
+
+    /// The side to move first in the next game: X in the first, then each
+    /// side in turn.
+    pub fn first(&self) -> Player {
+        match self.played() % 2 {
+            0 => Player::Player1,
+            _ => Player::Player2,
+        }
+    }
+
+    /// Who has won the match, once it is over. A match can end drawn.
+    pub fn winner(&self) -> Option<Player> {
+        if !self.over() {
+            return None;
+        }
+        match self.score.x.cmp(&self.score.o) {
+            Ordering::Greater => Some(Player::Player1),
+            Ordering::Less => Some(Player::Player2),
+            Ordering::Equal => None,
+        }
+    }
+
+    /// How the match stands, or how it ended.
+    pub fn summary(&self) -> String {
+        let Score { x, o, draws } = self.score;
+        if !self.over() {
+            return format!("Best of {}: {}", self.best_of, self.score);
+        }
+        let (high, low) = (x.max(o), x.min(o));
+        let result = match self.winner() {
+            Some(winner) => format!("{} takes the match", sigil(winner)),
+            None => "The match is drawn".to_string(),
+        };
+        match draws {
+            0 => format!("{}, {} to {}", result, high, low),
+            _ => format!("{}, {} to {} with {} drawn", result, high, low, draws),
+        }
+    }
+}
+
+fn sigil(player: Player) -> String {
+    player.get_sigil().to_string()
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn counts_finished_games() {
+        let mut score = Score::default();
+        score.record(&BoardState::Winner(Player::Player1, vec![0, 1, 2]));
+        score.record(&BoardState::Winner(Player::Player1, vec![]));
+        score.record(&BoardState::Tie);
+        score.record(&BoardState::InGame);
+        assert_eq!(score.to_string(), "X 2   O 0   Draws 1");
+    }
+    #[test]
+    fn a_match_ends_once_it_cannot_be_caught() {
+        let x_wins = BoardState::Winner(Player::Player1, vec![0, 1, 2]);
+        let mut series = Series::new(3);
+        assert_eq!(series.first(), Player::Player1);
+        series.record(&x_wins);
+        assert_eq!(series.first(), Player::Player2);
+        assert!(!series.over());
+        assert_eq!(series.summary(), "Best of 3: X 1   O 0   Draws 0");
+        series.record(&BoardState::Tie);
+        assert!(!series.over());
+        series.record(&x_wins);
+        assert!(series.over());
+        assert_eq!(series.winner(), Some(Player::Player1));
+        assert_eq!(series.summary(), "X takes the match, 2 to 0 with 1 drawn");
+        series.record(&x_wins);
+        assert_eq!(series.played(), 3);
+    }
+    #[test]
+    fn a_match_can_end_drawn() {
+        let mut series = Series::new(2);
+        series.record(&BoardState::Winner(Player::Player2, vec![]));
+        series.record(&BoardState::Winner(Player::Player1, vec![]));
+        assert_eq!(series.winner(), None);
+        assert_eq!(series.summary(), "The match is drawn, 1 to 1");
+        assert_eq!(next_length(1), 3);
+        assert_eq!(next_length(7), 1);
+        assert_eq!(length_name(5), "Best of 5");
+    }
+}
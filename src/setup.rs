@@ -0,0 +1,177 @@
+use crate::board::BoardState;
+use crate::brain;
+use crate::field::Field;
+use crate::rules::Rules;
+use nannou::rand::rngs::StdRng;
+use nannou::rand::seq::SliceRandom;
+use nannou::rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+/// A hand-made starting position for one board size.
+pub struct Preset {
+    pub name: &'static str,
+    pub width: usize,
+    pub height: usize,
+    pub cells: &'static [(usize, Field)],
+}
+
+impl Preset {
+    /// Whether this position was made for boards like `rules`.
+    pub fn fits(&self, rules: &Rules) -> bool {
+        (self.width, self.height) == (rules.width, rules.height)
+    }
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "Blocked Centre",
+        width: 3,
+        height: 3,
+        cells: &[(4, Field::Blocked)],
+    },
+    Preset {
+        name: "Blocked Corners",
+        width: 3,
+        height: 3,
+        cells: &[(0, Field::Blocked), (8, Field::Blocked)],
+    },
+    Preset {
+        name: "Centre Taken",
+        width: 3,
+        height: 3,
+        cells: &[(4, Field::X)],
+    },
+    Preset {
+        name: "Opposite Corners",
+        width: 3,
+        height: 3,
+        cells: &[(0, Field::X), (8, Field::O)],
+    },
+    Preset {
+        name: "Pillars",
+        width: 7,
+        height: 6,
+        cells: &[
+            (35, Field::Blocked),
+            (38, Field::Blocked),
+            (41, Field::Blocked),
+        ],
+    },
+];
+
+/// How the board is filled in before the first move.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Setup {
+    Empty,
+    /// One of the `PRESETS`, used when it fits the board.
+    Preset(usize),
+    /// A few blocked cells and a mark or two for each side, picked from the
+    /// seed.
+    Random(u64),
+}
+
+impl Default for Setup {
+    fn default() -> Self {
+        Setup::Empty
+    }
+}
+
+impl Setup {
+    /// The setup shown after this one when cycling through the menu, leaving
+    /// out presets for other boards than `rules`. `seed` is used when that
+    /// is a random one.
+    pub fn next(self, seed: u64, rules: &Rules) -> Setup {
+        let first = match self {
+            Setup::Empty => 0,
+            Setup::Preset(i) => i + 1,
+            Setup::Random(_) => return Setup::Empty,
+        };
+        (first..PRESETS.len())
+            .find(|&i| PRESETS[i].fits(rules))
+            .map(Setup::Preset)
+            .unwrap_or(Setup::Random(seed))
+    }
+
+    /// Turns down setups that name a preset there is none of, or one made
+    /// for another board than `rules`, as ones sent over the network may.
+    pub fn check(self, rules: &Rules) -> Result<(), String> {
+        match self {
+            Setup::Preset(i) => match PRESETS.get(i) {
+                None => Err(format!("there is no starting position {}", i)),
+                Some(preset) if !preset.fits(rules) => Err(format!(
+                    "{} is for a {} by {} board",
+                    preset.name, preset.width, preset.height
+                )),
+                Some(_) => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    pub fn apply(self, rules: &Rules, board: &mut [Field]) {
+        match self {
+            Setup::Empty => {}
+            Setup::Preset(i) => {
+                let preset = match PRESETS.get(i) {
+                    Some(preset) => preset,
+                    None => return,
+                };
+                if preset.fits(rules) {
+                    preset.cells.iter().for_each(|&(cell, v)| board[cell] = v);
+                }
+            }
+            Setup::Random(seed) => {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let mut cells: Vec<usize> = (0..rules.size()).collect();
+                cells.shuffle(&mut rng);
+                let mut cells = cells.into_iter();
+                cells
+                    .by_ref()
+                    .take((rules.size() / 9).max(1))
+                    .for_each(|cell| board[cell] = Field::Blocked);
+                // the same number for each side, so X still moves first
+                let pairs = (rules.size() / 18).max(1);
+                for &mark in [Field::X, Field::O].iter().cycle().take(2 * pairs) {
+                    // skipping cells that would hand a side a line before
+                    // the game starts. Under gravity a cell only picks the
+                    // column, and the mark drops down it like any other.
+                    let placed = cells.by_ref().any(|cell| {
+                        let target = if rules.gravity {
+                            rules.drop_target(board, rules.column(cell))
+                        } else {
+                            Some(cell)
+                        };
+                        let cell = match target {
+                            Some(cell) => cell,
+                            None => return false,
+                        };
+                        board[cell] = mark;
+                        let open =
+                            brain::check_winner(board, rules) == BoardState::InGame;
+                        if !open {
+                            board[cell] = Field::Empty;
+                        }
+                        open
+                    });
+                    if !placed {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl std::string::ToString for Setup {
+    fn to_string(&self) -> String {
+        match self {
+            Setup::Empty => "Empty".to_string(),
+            Setup::Preset(i) => match PRESETS.get(*i) {
+                Some(preset) => preset.name.to_string(),
+                None => format!("Preset #{}", i),
+            },
+            Setup::Random(seed) => format!("Random #{}", seed),
+        }
+    }
+}
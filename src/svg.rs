@@ -0,0 +1,348 @@
+//! Boards written out as SVG pictures, laid out the way `board_display`
+//! draws them, for documents and slides. Nothing here opens a window, so it
+//! works on machines without a GPU.
+use crate::analysis;
+use crate::board::{Board, BoardState};
+use crate::config::Color;
+use crate::field::Field;
+use crate::hex;
+use crate::rules::Move;
+use crate::theme::Theme;
+use nannou::prelude::*;
+use std::fmt::Write;
+
+/// How exported pictures look.
+#[derive(Debug, Clone)]
+pub struct Export {
+    /// The side of the square picture, in pixels.
+    pub size: f32,
+    pub theme: Theme,
+    /// Whether each mark is numbered with the move that made it.
+    pub move_numbers: bool,
+}
+
+impl Default for Export {
+    fn default() -> Self {
+        Export {
+            size: 600.0,
+            theme: Theme::light(),
+            move_numbers: false,
+        }
+    }
+}
+
+/// The position on `board` as an SVG document. The keyboard cursor and a
+/// piece picked up to slide are left out, as they aren't part of the game.
+pub fn position(board: &Board, export: &Export) -> String {
+    let rect = Rect::from_w_h(export.size, export.size);
+    let style = &export.theme.board;
+    let mut canvas = Canvas::new(rect);
+    canvas.rect(rect.xy(), rect.w(), rect.h(), export.theme.background.0);
+    // the grid
+    if board.rules.hex {
+        let size = hex::size(&rect, &board.rules);
+        for cell in 0..board.rules.size() {
+            let corners = hex::corners(board.cell_center(&rect, cell), size);
+            canvas.outline(&corners, style.grid_weight, style.grid.0);
+        }
+    } else {
+        let (cell_width, cell_height) = board.cell_size(&rect);
+        for column in 1..board.rules.width {
+            let x = rect.left() + column as f32 * cell_width;
+            let (top, bottom) = (pt2(x, rect.top()), pt2(x, rect.bottom()));
+            canvas.line(top, bottom, style.grid_weight, style.grid.0);
+        }
+        for row in 1..board.rules.height {
+            let y = rect.top() - row as f32 * cell_height;
+            let (left, right) = (pt2(rect.left(), y), pt2(rect.right(), y));
+            canvas.line(left, right, style.grid_weight, style.grid.0);
+        }
+    }
+    // the marks
+    let (cell_width, cell_height) = board.cell_size(&rect);
+    let dims = cell_width.min(cell_height);
+    let vanishing = board.vanishing();
+    let numbers = move_numbers(board);
+    for (cell, &field) in board.board.iter().enumerate() {
+        let center = board.cell_center(&rect, cell);
+        match field {
+            Field::Empty => continue,
+            Field::Blocked => {
+                if board.rules.hex {
+                    let size = hex::size(&rect, &board.rules);
+                    canvas.polygon(&hex::corners(center, size), style.blocked.0);
+                } else {
+                    canvas.rect(center, cell_width, cell_height, style.blocked.0);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let opacity = if vanishing.contains(&cell) {
+            style.faded.clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let label = field.to_string();
+        canvas.text(center, dims, &label, style.mark(field), opacity);
+        if style.shapes {
+            canvas.outline(&cue(center, dims, field), 3.0, style.mark(field));
+        }
+        if let Some(number) = numbers[cell].filter(|_| export.move_numbers) {
+            // in the top left corner, clear of the mark
+            let corner = center + vec2(-dims * 0.36, dims * 0.36);
+            let label = number.to_string();
+            canvas.text(corner, dims / 6.0, &label, export.theme.text.0, 1.0);
+        }
+    }
+    // the result
+    let result_size = rect.w().min(rect.h()) / 8.0;
+    let result = match &board.state {
+        BoardState::InGame => None,
+        BoardState::Tie => Some("Tie!".to_string()),
+        BoardState::Winner(winner, line) => {
+            if line.len() > 1 {
+                for (start, end) in board.winner_segments(&rect, line) {
+                    canvas.line(start, end, style.line_weight, style.line.0);
+                }
+            }
+            Some(format!("{} Wins!", winner.get_sigil().to_string()))
+        }
+    };
+    if let Some(result) = result {
+        canvas.text(rect.xy(), result_size, &result, style.result.0, 1.0);
+    }
+    canvas.finish()
+}
+
+/// Every position of the game on `board`, from the start to the end, as
+/// SVG documents. `None` for boards whose moves aren't all known, like
+/// those mirrored from the server.
+pub fn game(board: &Board, export: &Export) -> Option<Vec<String>> {
+    let positions = analysis::replay(board)?;
+    Some(positions.iter().map(|p| position(p, export)).collect())
+}
+
+/// The move that made each cell's mark, counted from 1. Marks from the
+/// starting position have none.
+pub fn move_numbers(board: &Board) -> Vec<Option<usize>> {
+    let mut numbers = vec![None; board.board.len()];
+    for (index, &play) in board.moves.iter().enumerate() {
+        if let Move::Slide(from, _) = play {
+            numbers[from] = None;
+        }
+        numbers[play.target()] = Some(index + 1);
+    }
+    // marks that have vanished since take their numbers with them
+    for (cell, &field) in board.board.iter().enumerate() {
+        if field == Field::Empty {
+            numbers[cell] = None;
+        }
+    }
+    numbers
+}
+
+/// The diamond around X or the square around O, as `show_cue` draws it.
+fn cue(center: Point2, dims: f32, field: Field) -> Vec<Point2> {
+    let half = dims * 0.42;
+    match field {
+        Field::X => vec![
+            pt2(center.x, center.y + half),
+            pt2(center.x + half, center.y),
+            pt2(center.x, center.y - half),
+            pt2(center.x - half, center.y),
+        ],
+        _ => {
+            let half = half * 0.8;
+            vec![
+                pt2(center.x - half, center.y + half),
+                pt2(center.x + half, center.y + half),
+                pt2(center.x + half, center.y - half),
+                pt2(center.x - half, center.y - half),
+            ]
+        }
+    }
+}
+
+/// An SVG document being written, taking points in the app's coordinates,
+/// with the origin in the middle of `rect` and y going up.
+struct Canvas {
+    rect: Rect,
+    out: String,
+}
+
+impl Canvas {
+    fn new(rect: Rect) -> Canvas {
+        let mut out = String::new();
+        let (width, height) = (rect.w(), rect.h());
+        writeln!(
+            out,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+             viewBox=\"0 0 {} {}\">",
+            width, height, width, height
+        )
+        .unwrap();
+        Canvas { rect, out }
+    }
+
+    /// Where `point` is in the picture, whose y goes down from the top.
+    fn place(&self, point: Point2) -> (f32, f32) {
+        (point.x - self.rect.left(), self.rect.top() - point.y)
+    }
+
+    fn points(&self, points: &[Point2]) -> String {
+        let places: Vec<String> = points
+            .iter()
+            .map(|&point| {
+                let (x, y) = self.place(point);
+                format!("{:.1},{:.1}", x, y)
+            })
+            .collect();
+        places.join(" ")
+    }
+
+    fn rect(&mut self, center: Point2, width: f32, height: f32, color: Srgb<u8>) {
+        let (x, y) = self.place(center + vec2(-width / 2.0, height / 2.0));
+        writeln!(
+            self.out,
+            "  <rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" \
+             fill=\"{}\"/>",
+            x,
+            y,
+            width,
+            height,
+            Color(color)
+        )
+        .unwrap();
+    }
+
+    fn polygon(&mut self, corners: &[Point2], color: Srgb<u8>) {
+        let points = self.points(corners);
+        writeln!(
+            self.out,
+            "  <polygon points=\"{}\" fill=\"{}\"/>",
+            points,
+            Color(color)
+        )
+        .unwrap();
+    }
+
+    fn outline(&mut self, corners: &[Point2], weight: f32, color: Srgb<u8>) {
+        let points = self.points(corners);
+        writeln!(
+            self.out,
+            "  <polygon points=\"{}\" fill=\"none\" stroke=\"{}\" \
+             stroke-width=\"{}\"/>",
+            points,
+            Color(color),
+            weight
+        )
+        .unwrap();
+    }
+
+    fn line(&mut self, start: Point2, end: Point2, weight: f32, color: Srgb<u8>) {
+        let ((x1, y1), (x2, y2)) = (self.place(start), self.place(end));
+        writeln!(
+            self.out,
+            "  <line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" \
+             stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\"/>",
+            x1,
+            y1,
+            x2,
+            y2,
+            Color(color),
+            weight
+        )
+        .unwrap();
+    }
+
+    /// Writing centered on `center`, `size` pixels high.
+    fn text(
+        &mut self,
+        center: Point2,
+        size: f32,
+        label: &str,
+        color: Srgb<u8>,
+        opacity: f32,
+    ) {
+        let (x, y) = self.place(center);
+        writeln!(
+            self.out,
+            "  <text x=\"{:.1}\" y=\"{:.1}\" font-family=\"sans-serif\" \
+             font-size=\"{:.1}\" text-anchor=\"middle\" \
+             dominant-baseline=\"central\" fill=\"{}\" fill-opacity=\"{}\">{}</text>",
+            x,
+            y,
+            size,
+            Color(color),
+            opacity,
+            label
+        )
+        .unwrap();
+    }
+
+    fn finish(mut self) -> String {
+        self.out.push_str("</svg>\n");
+        self.out
+    }
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use crate::board::test::board;
+    #[allow(unused_imports)]
+    use crate::rules::{Rules, Variant};
+
+    #[allow(dead_code)]
+    fn won_game() -> Board {
+        let mut board = board(Rules::classic());
+        for &cell in &[0, 4, 1, 8, 2] {
+            board.play(Move::Place(cell));
+        }
+        board
+    }
+
+    #[test]
+    fn a_position_has_its_grid_marks_and_winning_line() {
+        let export = Export {
+            move_numbers: true,
+            ..Export::default()
+        };
+        let svg = position(&won_game(), &export);
+        assert!(
+            svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"600\"")
+        );
+        assert!(svg.ends_with("</svg>\n"));
+        // two lines each way for the grid, then the winning line
+        assert_eq!(svg.matches("<line").count(), 5);
+        assert!(svg.contains(
+            "<line x1=\"0.0\" y1=\"100.0\" x2=\"600.0\" y2=\"100.0\" stroke=\"#000000\""
+        ));
+        assert_eq!(svg.matches(">X</text>").count(), 3);
+        assert_eq!(svg.matches(">O</text>").count(), 2);
+        assert!(svg.contains(">5</text>"));
+        assert!(svg.contains(">X Wins!</text>"));
+    }
+    #[test]
+    fn a_game_is_one_picture_per_position() {
+        let frames = game(&won_game(), &Export::default()).unwrap();
+        assert_eq!(frames.len(), 6);
+        assert_eq!(frames[0].matches("</text>").count(), 0);
+        assert_eq!(frames[3].matches("</text>").count(), 3);
+        assert!(!frames[3].contains("Wins!"));
+    }
+    #[test]
+    fn numbers_follow_slides_and_vanishing_marks() {
+        let mut board = board(Variant::Infinite.rules());
+        for &cell in &[0, 4, 1, 5, 8, 2, 6] {
+            board.play(Move::Place(cell));
+        }
+        let numbers = move_numbers(&board);
+        // X's first mark, in cell 0, made way for its fourth
+        assert_eq!(numbers[0], None);
+        assert_eq!(numbers[6], Some(7));
+        assert_eq!(numbers[1], Some(3));
+    }
+}
@@ -0,0 +1,376 @@
+//! How the game looks: the colors and line weights of the menus and the
+//! board. There are a few built in themes, and more can be added as TOML
+//! files in the themes directory, each naming only what it changes from
+//! `light`:
+//!
+//! ```toml
+//! name = "forest"
+//! background = "#1f3d2b"
+//! text = "#f0f0e0"
+//!
+//! [button]
+//! fill = "#2f5d3f"
+//! hover = "#3f7552"
+//! pressed = "#1f4530"
+//! label = "#f0f0e0"
+//! border = "#f0f0e0"
+//! border_weight = 2.0
+//!
+//! [board]
+//! grid = "#f0f0e0"
+//! x = "#ffd166"
+//! o = "#f0f0e0"
+//! ```
+use crate::config::{Color, Colors};
+use crate::field::Field;
+use nannou::prelude::*;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Where user themes are looked for unless the settings say otherwise.
+pub const DEFAULT_DIRECTORY: &str = "themes";
+
+/// The built in theme that `accessibility.high_contrast` turns on.
+pub const HIGH_CONTRAST: &str = "high-contrast";
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    /// Titles, statuses and other writing straight on the background.
+    pub text: Color,
+    pub button: ButtonStyle,
+    /// Rows of lists, such as open games and profiles.
+    pub row: Color,
+    /// Where names are typed, and the clock of the side to move.
+    pub field: Color,
+    /// The outline around the button picked with the keyboard.
+    pub focus: Color,
+    pub board: BoardStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ButtonStyle {
+    pub fill: Color,
+    /// The fill under the mouse.
+    pub hover: Color,
+    /// The fill while the mouse is held down on it.
+    pub pressed: Color,
+    pub label: Color,
+    pub border: Color,
+    /// No border is drawn at 0.
+    pub border_weight: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoardStyle {
+    pub grid: Color,
+    pub grid_weight: f32,
+    pub x: Color,
+    pub o: Color,
+    /// How opaque marks about to vanish are, from 0 to 1.
+    pub faded: f32,
+    pub blocked: Color,
+    /// The piece picked up to slide.
+    pub selected: Color,
+    /// The cell under the keyboard cursor.
+    pub cursor: Color,
+    pub line: Color,
+    pub line_weight: f32,
+    /// The "X Wins!" or "Tie!" written over a finished game.
+    pub result: Color,
+    /// Whether to frame X in a diamond and O in a square, so the sides
+    /// differ in more than glyph and color.
+    pub shapes: bool,
+}
+
+fn hex(red: u8, green: u8, blue: u8) -> Color {
+    Color(srgb(red, green, blue))
+}
+
+impl Default for ButtonStyle {
+    fn default() -> Self {
+        ButtonStyle {
+            fill: Color(DARKGREY),
+            hover: Color(SILVER),
+            pressed: Color(GREY),
+            label: Color(BLACK),
+            border: Color(BLACK),
+            border_weight: 0.0,
+        }
+    }
+}
+
+impl Default for BoardStyle {
+    fn default() -> Self {
+        BoardStyle {
+            grid: Color(DARKGREY),
+            grid_weight: 2.0,
+            x: Color(BLACK),
+            o: Color(BLACK),
+            faded: 0.3,
+            blocked: Color(DARKGREY),
+            selected: Color(LIGHTSKYBLUE),
+            cursor: Color(LIGHTYELLOW),
+            line: Color(BLACK),
+            line_weight: 2.0,
+            result: Color(WHITE),
+            shapes: false,
+        }
+    }
+}
+
+/// Looks like `light`, but has no name until one is given.
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            name: String::new(),
+            ..Theme::light()
+        }
+    }
+}
+
+impl BoardStyle {
+    /// The color of `field`'s marks.
+    pub fn mark(&self, field: Field) -> Srgb<u8> {
+        match field {
+            Field::X => self.x.0,
+            _ => self.o.0,
+        }
+    }
+
+    /// The color of `field`'s marks that are about to vanish.
+    pub fn faded_mark(&self, field: Field) -> Srgba<u8> {
+        let color = self.mark(field);
+        let alpha = (self.faded.clamp(0.0, 1.0) * 255.0) as u8;
+        srgba(color.red, color.green, color.blue, alpha)
+    }
+}
+
+impl Theme {
+    /// Sky blue and grey, as the game has always looked.
+    pub fn light() -> Theme {
+        Theme {
+            name: "light".to_string(),
+            background: Color(SKYBLUE),
+            text: Color(BLACK),
+            button: ButtonStyle::default(),
+            row: Color(LIGHTGREY),
+            field: Color(WHITE),
+            focus: Color(BLACK),
+            board: BoardStyle::default(),
+        }
+    }
+
+    /// Light marks on a dark slate, easier on the eyes at night.
+    pub fn dark() -> Theme {
+        let ink = hex(0xe8, 0xe8, 0xe8);
+        Theme {
+            name: "dark".to_string(),
+            background: hex(0x1e, 0x1e, 0x24),
+            text: ink,
+            button: ButtonStyle {
+                fill: hex(0x3a, 0x3a, 0x46),
+                hover: hex(0x4a, 0x4a, 0x59),
+                pressed: hex(0x2a, 0x2a, 0x33),
+                label: ink,
+                border: hex(0x5a, 0x5a, 0x6a),
+                border_weight: 1.0,
+            },
+            row: hex(0x2c, 0x2c, 0x34),
+            field: hex(0x44, 0x44, 0x4f),
+            focus: hex(0xff, 0xd8, 0x66),
+            board: BoardStyle {
+                grid: hex(0x8a, 0x8a, 0x99),
+                x: hex(0xff, 0x8c, 0x69),
+                o: hex(0x7e, 0xc8, 0xe3),
+                blocked: hex(0x55, 0x55, 0x5f),
+                selected: hex(0x2f, 0x4f, 0x6f),
+                cursor: hex(0x5f, 0x5a, 0x2f),
+                line: ink,
+                line_weight: 3.0,
+                result: Color(WHITE),
+                ..BoardStyle::default()
+            },
+        }
+    }
+
+    /// Pen on cream paper, with red and blue ink for the two sides.
+    pub fn paper() -> Theme {
+        let pencil = hex(0x2b, 0x2b, 0x2b);
+        let red_ink = hex(0x8b, 0x1a, 0x1a);
+        Theme {
+            name: "paper".to_string(),
+            background: hex(0xf4, 0xec, 0xd8),
+            text: pencil,
+            button: ButtonStyle {
+                fill: hex(0xe6, 0xdc, 0xc3),
+                hover: hex(0xef, 0xe7, 0xd3),
+                pressed: hex(0xd4, 0xc8, 0xab),
+                label: pencil,
+                border: pencil,
+                border_weight: 2.0,
+            },
+            row: hex(0xeb, 0xe2, 0xcc),
+            field: hex(0xff, 0xfa, 0xf0),
+            focus: red_ink,
+            board: BoardStyle {
+                grid: pencil,
+                grid_weight: 1.5,
+                x: hex(0x20, 0x30, 0x5f),
+                o: red_ink,
+                blocked: hex(0xb8, 0xad, 0x94),
+                selected: hex(0xd8, 0xcf, 0xae),
+                cursor: hex(0xef, 0xe3, 0xa8),
+                line: red_ink,
+                line_weight: 3.0,
+                result: pencil,
+                ..BoardStyle::default()
+            },
+        }
+    }
+
+    /// Black on white and on yellow, with Okabe and Ito's vermillion and
+    /// blue for the sides, which stay apart for every kind of color vision.
+    pub fn high_contrast() -> Theme {
+        Theme {
+            name: HIGH_CONTRAST.to_string(),
+            background: Color(WHITE),
+            text: Color(BLACK),
+            button: ButtonStyle {
+                fill: hex(0xf0, 0xe4, 0x42),
+                hover: hex(0xff, 0xf5, 0x80),
+                pressed: hex(0xc8, 0xbd, 0x20),
+                label: Color(BLACK),
+                border: Color(BLACK),
+                border_weight: 2.0,
+            },
+            row: hex(0xdd, 0xdd, 0xdd),
+            field: Color(WHITE),
+            focus: Color(BLACK),
+            board: BoardStyle {
+                grid: Color(BLACK),
+                grid_weight: 3.0,
+                x: hex(0xd5, 0x5e, 0x00),
+                o: hex(0x00, 0x72, 0xb2),
+                cursor: Color(GOLD),
+                line: Color(BLACK),
+                line_weight: 6.0,
+                result: Color(BLACK),
+                shapes: true,
+                ..BoardStyle::default()
+            },
+        }
+    }
+
+    /// Reads a theme written as TOML. One without a name is named after
+    /// its file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Theme, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let mut theme: Theme =
+            toml::from_str(&text).map_err(|error| error.to_string())?;
+        if theme.name.is_empty() {
+            if let Some(stem) = path.file_stem() {
+                theme.name = stem.to_string_lossy().into_owned();
+            }
+        }
+        Ok(theme)
+    }
+
+    /// The colors the settings pick, in place of this theme's own.
+    pub fn with_colors(mut self, colors: &Colors) -> Theme {
+        if let Some(background) = colors.background {
+            self.background = background;
+        }
+        if let Some(button) = colors.button {
+            self.button.fill = button;
+        }
+        if let Some(text) = colors.text {
+            self.text = text;
+            self.button.label = text;
+        }
+        self
+    }
+}
+
+/// The built in themes, then any in `directory`, in order of their file
+/// names. A file that can't be read is reported and skipped, and one with
+/// the name of a built in theme takes its place.
+pub fn load_all(directory: impl AsRef<Path>) -> Vec<Theme> {
+    let mut themes = vec![
+        Theme::light(),
+        Theme::dark(),
+        Theme::paper(),
+        Theme::high_contrast(),
+    ];
+    let directory = directory.as_ref();
+    let mut paths: Vec<_> = match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .iter()
+                    .any(|&extension| extension == "toml")
+            })
+            .collect(),
+        Err(ref error) if error.kind() == io::ErrorKind::NotFound => vec![],
+        Err(error) => {
+            eprintln!("could not read {}: {}", directory.display(), error);
+            vec![]
+        }
+    };
+    paths.sort();
+    for path in paths {
+        match Theme::load(&path) {
+            Ok(theme) => {
+                match themes.iter().position(|known| known.name == theme.name) {
+                    Some(index) => themes[index] = theme,
+                    None => themes.push(theme),
+                }
+            }
+            Err(error) => {
+                eprintln!("could not use the theme {}: {}", path.display(), error)
+            }
+        }
+    }
+    themes
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use std::env;
+
+    #[test]
+    fn theme_files_change_only_what_they_name() {
+        let text = "background = \"#102030\"\n[board]\nline_weight = 4.0\n";
+        let theme: Theme = toml::from_str(text).unwrap();
+        assert_eq!(theme.background, hex(0x10, 0x20, 0x30));
+        assert_eq!(theme.board.line_weight, 4.0);
+        assert_eq!(theme.button, Theme::light().button);
+    }
+    #[test]
+    fn user_themes_join_the_built_in_ones() {
+        let directory = env::temp_dir().join(format!("themes-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("forest.toml"), "text = \"#f0f0e0\"\n").unwrap();
+        fs::write(directory.join("dark.toml"), "name = \"dark\"\n").unwrap();
+        fs::write(directory.join("broken.toml"), "text = 3\n").unwrap();
+        let themes = load_all(&directory);
+        fs::remove_dir_all(&directory).unwrap();
+        let names: Vec<&str> = themes.iter().map(|theme| theme.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["light", "dark", "paper", HIGH_CONTRAST, "forest"]
+        );
+        assert_eq!(themes[1].background, Theme::light().background);
+        assert_eq!(themes[4].text, hex(0xf0, 0xf0, 0xe0));
+    }
+}
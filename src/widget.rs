@@ -0,0 +1,172 @@
+//! Buttons and menus that both draw themselves and tell which of them is
+//! under a point, so a screen only has to say where its buttons go and what
+//! they are labelled.
+use crate::theme::ButtonStyle;
+use nannou::prelude::*;
+
+/// How a button looks, following the mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonState {
+    Idle,
+    Hovered,
+    Pressed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Button {
+    pub area: Rect,
+    pub label: String,
+}
+
+impl Button {
+    pub fn new(area: Rect, label: impl Into<String>) -> Button {
+        Button {
+            area,
+            label: label.into(),
+        }
+    }
+
+    pub fn contains(&self, point: Point2) -> bool {
+        self.area.contains(point)
+    }
+
+    /// Pressed while the mouse is held down over it, hovered while it is
+    /// only over it.
+    pub fn state(&self, mouse: Point2, held: bool) -> ButtonState {
+        match self.contains(mouse) {
+            true if held => ButtonState::Pressed,
+            true => ButtonState::Hovered,
+            false => ButtonState::Idle,
+        }
+    }
+
+    pub fn draw(
+        &self,
+        draw: &app::Draw,
+        style: &ButtonStyle,
+        font_size: u32,
+        state: ButtonState,
+    ) {
+        let fill = match state {
+            ButtonState::Idle => style.fill,
+            ButtonState::Hovered => style.hover,
+            ButtonState::Pressed => style.pressed,
+        };
+        let area = self.area;
+        draw.rect().xy(area.xy()).wh(area.wh()).color(fill.0);
+        if style.border_weight > 0.0 {
+            outline(draw, area, 0.0, style.border_weight, style.border.0);
+        }
+        let label = text(&self.label).font_size(font_size).build(area);
+        draw.path()
+            .fill()
+            .color(style.label.0)
+            .events(label.path_events());
+    }
+}
+
+/// Buttons stacked top to bottom, told apart by their place in the stack.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Menu {
+    pub buttons: Vec<Button>,
+}
+
+impl Menu {
+    /// One button of `size` for each of `labels`, `gap` apart, in a column
+    /// centered on `center`.
+    pub fn vertical(center: Point2, size: Vector2, gap: f32, labels: &[&str]) -> Menu {
+        let buttons = column(center, size, gap, labels.len())
+            .into_iter()
+            .zip(labels)
+            .map(|(area, &label)| Button::new(area, label))
+            .collect();
+        Menu { buttons }
+    }
+
+    /// Which button is under `point`, if any.
+    pub fn hit(&self, point: Point2) -> Option<usize> {
+        self.buttons
+            .iter()
+            .position(|button| button.contains(point))
+    }
+
+    pub fn draw(
+        &self,
+        draw: &app::Draw,
+        style: &ButtonStyle,
+        font_size: u32,
+        mouse: Point2,
+        held: bool,
+    ) {
+        for button in &self.buttons {
+            button.draw(draw, style, font_size, button.state(mouse, held));
+        }
+    }
+}
+
+/// The places of `count` boxes of `size`, `gap` apart, in a column centered
+/// on `center`, from the top.
+pub fn column(center: Point2, size: Vector2, gap: f32, count: usize) -> Vec<Rect> {
+    let height = count as f32 * size.y + count.saturating_sub(1) as f32 * gap;
+    let top = center.y + height / 2.0;
+    (0..count)
+        .map(|i| {
+            let y = top - size.y / 2.0 - i as f32 * (size.y + gap);
+            Rect::from_x_y_w_h(center.x, y, size.x, size.y)
+        })
+        .collect()
+}
+
+/// The places of `count` boxes of `size`, `gap` apart, in a row centered on
+/// `center`, from the left.
+pub fn row(center: Point2, size: Vector2, gap: f32, count: usize) -> Vec<Rect> {
+    let width = count as f32 * size.x + count.saturating_sub(1) as f32 * gap;
+    let left = center.x - width / 2.0;
+    (0..count)
+        .map(|i| {
+            let x = left + size.x / 2.0 + i as f32 * (size.x + gap);
+            Rect::from_x_y_w_h(x, center.y, size.x, size.y)
+        })
+        .collect()
+}
+
+/// Draws a frame `margin` outside `area`.
+pub fn outline(draw: &app::Draw, area: Rect, margin: f32, weight: f32, color: Srgb<u8>) {
+    let (left, right) = (area.left() - margin, area.right() + margin);
+    let (bottom, top) = (area.bottom() - margin, area.top() + margin);
+    draw.path()
+        .stroke()
+        .weight(weight)
+        .color(color)
+        .points_closed(vec![
+            pt2(left, top),
+            pt2(right, top),
+            pt2(right, bottom),
+            pt2(left, bottom),
+        ]);
+}
+
+mod test {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn a_vertical_menu_stacks_its_buttons_from_the_top() {
+        let menu = Menu::vertical(pt2(0.0, 0.0), vec2(100.0, 40.0), 10.0, &["A", "B"]);
+        let ys: Vec<f32> = menu.buttons.iter().map(|button| button.area.y()).collect();
+        assert_eq!(ys, vec![25.0, -25.0]);
+        assert_eq!(menu.hit(pt2(30.0, 30.0)), Some(0));
+        assert_eq!(menu.hit(pt2(0.0, -40.0)), Some(1));
+        assert_eq!(menu.hit(pt2(0.0, 0.0)), None);
+        assert_eq!(menu.hit(pt2(60.0, 25.0)), None);
+    }
+    #[test]
+    fn buttons_follow_the_mouse() {
+        let button = Button::new(Rect::from_w_h(100.0, 40.0), "Go");
+        assert_eq!(button.state(pt2(0.0, 0.0), false), ButtonState::Hovered);
+        assert_eq!(button.state(pt2(0.0, 0.0), true), ButtonState::Pressed);
+        assert_eq!(button.state(pt2(0.0, 50.0), true), ButtonState::Idle);
+        let places = row(pt2(0.0, 0.0), vec2(100.0, 40.0), 20.0, 2);
+        assert_eq!((places[0].x(), places[1].x()), (-60.0, 60.0));
+    }
+}